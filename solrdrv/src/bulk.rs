@@ -0,0 +1,504 @@
+use std::time::{Duration, Instant};
+use futures::future::{self, Either};
+use futures::stream::{self, Stream, StreamExt};
+use serde_json::Value;
+use crate::{nested, Auth, Collection, CommitOptions, RequestOptions, SolrError, UpdateCommand};
+
+/// The maximum delay between retries of a batch.
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+/// Statistics of a single batch sent by a `BulkIndexer`
+pub struct BatchReport {
+    /// The sequence number of the batch, starting from `0`.
+    pub index: usize,
+    /// The number of documents in the batch.
+    pub docs: usize,
+    /// The size of the request body in bytes.
+    pub bytes: usize,
+    /// The number of requests made (`1` if the batch succeeded on the first attempt).
+    pub attempts: u32,
+    /// The time it took to send the batch, including retries.
+    pub elapsed: Duration,
+    /// Set if the batch failed after all retries.
+    pub error: Option<SolrError>,
+}
+
+#[derive(Debug)]
+/// Documents which could not be indexed by a `BulkIndexer`
+pub struct BatchFailure {
+    /// The sequence number of the batch, or `None` if the document was rejected before being
+    /// batched (e.g. because it is not an object).
+    pub index: Option<usize>,
+    /// The documents which were not indexed.
+    pub docs: Vec<Value>,
+    /// The error of the last attempt.
+    pub error: SolrError,
+}
+
+#[derive(Debug, Default)]
+/// A summary of a `BulkIndexer` run
+pub struct BulkReport {
+    /// The number of batches sent.
+    pub batches: usize,
+    /// The number of successfully indexed documents.
+    pub docs_indexed: usize,
+    /// The number of documents which were not indexed.
+    pub docs_failed: usize,
+    /// The total size of successfully sent request bodies in bytes.
+    pub bytes: usize,
+    /// The number of retried requests.
+    pub retries: usize,
+    /// The time it took to index all documents.
+    pub elapsed: Duration,
+    /// The failed batches and rejected documents.
+    pub failures: Vec<BatchFailure>,
+}
+
+/// A batch of documents ready to be sent, serialized as `"add"` members of an update body. The
+/// documents are kept to be reported if the batch fails.
+struct Batch {
+    index: usize,
+    members: Vec<String>,
+    docs: Vec<Value>,
+    bytes: usize,
+}
+
+impl Batch {
+    fn new(index: usize) -> Batch {
+        Batch { index, members: vec![], docs: vec![], bytes: 0 }
+    }
+
+    fn push(&mut self, member: String, doc: Value) {
+        self.bytes += member.len();
+        self.members.push(member);
+        self.docs.push(doc);
+    }
+}
+
+/// An item produced by the batching stage of a `BulkIndexer`
+enum Batched {
+    Batch(Batch),
+    Rejected(Value, SolrError),
+}
+
+/// An outcome of a single item produced by the batching stage of a `BulkIndexer`
+enum Outcome {
+    Sent(BatchReport, Batch),
+    Rejected(Value, SolrError),
+}
+
+/// A state of the batching stage of a `BulkIndexer`
+struct BatcherState<S> {
+    docs: S,
+    batch: Batch,
+    deadline: Option<tokio::time::Instant>,
+    done: bool,
+}
+
+#[derive(Debug)]
+/// An indexer which sends documents from a stream (or a channel) into a collection in batches,
+/// running multiple update requests concurrently
+pub struct BulkIndexer<'a, 'b> {
+    collection: &'a Collection<'b>,
+    batch_size: usize,
+    batch_bytes: usize,
+    flush_interval: Option<Duration>,
+    concurrency: usize,
+    max_retries: u32,
+    retry_backoff: Duration,
+    commit_options: CommitOptions,
+    options: RequestOptions,
+}
+
+impl<'a, 'b> BulkIndexer<'a, 'b> {
+    pub(crate) fn new(collection: &'a Collection<'b>) -> BulkIndexer<'a, 'b> {
+        BulkIndexer {
+            collection,
+            batch_size: 1000,
+            batch_bytes: 10 * 1024 * 1024,
+            flush_interval: None,
+            concurrency: 4,
+            max_retries: 3,
+            retry_backoff: Duration::from_millis(500),
+            commit_options: CommitOptions::none(),
+            options: collection.options.clone(),
+        }
+    }
+
+    /// Sets the maximum number of documents in a batch. Defaults to 1000.
+    ///
+    /// # Arguments
+    /// * `batch_size` -
+    pub fn batch_size(&mut self, batch_size: usize) -> &mut Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Sets the maximum size of a batch in bytes. A single document larger than this is sent in
+    /// a batch on its own. Defaults to 10 MiB.
+    ///
+    /// # Arguments
+    /// * `batch_bytes` -
+    pub fn batch_bytes(&mut self, batch_bytes: usize) -> &mut Self {
+        self.batch_bytes = batch_bytes;
+        self
+    }
+
+    /// Sets the maximum time a document waits in an incomplete batch before the batch is sent.
+    /// By default, a batch is sent only when it is full or when the input ends.
+    ///
+    /// # Arguments
+    /// * `flush_interval` -
+    pub fn flush_interval(&mut self, flush_interval: Duration) -> &mut Self {
+        self.flush_interval = Some(flush_interval);
+        self
+    }
+
+    /// Sets the maximum number of concurrent update requests. When all of them are in flight, no
+    /// more documents are taken from the input. Defaults to 4.
+    ///
+    /// # Arguments
+    /// * `concurrency` -
+    pub fn concurrency(&mut self, concurrency: usize) -> &mut Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Sets how many times a batch is retried after a transport error, a server error (HTTP status
+    /// 5xx) or HTTP status 429. Defaults to 3.
+    ///
+    /// # Arguments
+    /// * `max_retries` -
+    pub fn max_retries(&mut self, max_retries: u32) -> &mut Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets the delay before the first retry of a batch, which doubles with every next retry up to
+    /// 30 s. Defaults to 500 ms.
+    ///
+    /// # Arguments
+    /// * `retry_backoff` -
+    pub fn retry_backoff(&mut self, retry_backoff: Duration) -> &mut Self {
+        self.retry_backoff = retry_backoff;
+        self
+    }
+
+    /// Sets options of the commit following each batch. Defaults to no commit, so either use
+    /// `CommitOptions::within` or call `Collection::hard_commit` after the run.
+    ///
+    /// # Arguments
+    /// * `options` -
+    pub fn commit_options(&mut self, options: &CommitOptions) -> &mut Self {
+        self.commit_options = options.clone();
+        self
+    }
+
+    /// Overrides the authentication of update requests. Defaults to the collection's one (see
+    /// `Collection::auth`).
+    ///
+    /// # Arguments
+    /// * `auth` -
+    pub fn auth(&mut self, auth: Auth) -> &mut Self {
+        self.options.auth(auth);
+        self
+    }
+
+    /// Sets a header sent with update requests.
+    ///
+    /// # Arguments
+    /// * `name` - The header name.
+    /// * `value` - The header value.
+    pub fn header(&mut self, name: String, value: String) -> &mut Self {
+        self.options.header(name, value);
+        self
+    }
+
+    /// Indexes all documents from a stream and returns a summary of the run.
+    ///
+    /// # Arguments
+    /// * `docs` - A stream of document objects, e.g. a `tokio::sync::mpsc::Receiver`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # async fn produce_documents(tx: tokio::sync::mpsc::Sender<solrdrv::serde_json::Value>) {}
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+    /// # let mut users = solr.collections().get("users".into()).await?;
+    /// let (tx, rx) = tokio::sync::mpsc::channel(10_000);
+    /// tokio::spawn(produce_documents(tx));
+    ///
+    /// let report = users.bulk_indexer()
+    ///     .batch_size(5000)
+    ///     .flush_interval(Duration::from_secs(1))
+    ///     .concurrency(8)
+    ///     .run(rx).await?;
+    /// users.hard_commit().await?;
+    /// println!("Indexed {} documents, {} failed", report.docs_indexed, report.docs_failed);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn run<S>(&self, docs: S) -> Result<BulkReport, SolrError>
+        where S: Stream<Item = Value> + Unpin {
+        self.run_with(docs, |_| {}).await
+    }
+
+    /// Same as `run`, but calls `on_batch` with statistics of each batch once it is sent.
+    ///
+    /// # Arguments
+    /// * `docs` - A stream of document objects.
+    /// * `on_batch` - A callback receiving statistics of each batch.
+    ///
+    /// # Return
+    /// If the indexer is misconfigured (e.g. `batch_size` or `concurrency` is zero), then returns
+    /// `SolrError::InvalidInput`. Failures of individual batches do not fail the run, but are
+    /// listed in `BulkReport::failures`.
+    pub async fn run_with<S, F>(&self, docs: S, mut on_batch: F) -> Result<BulkReport, SolrError>
+        where S: Stream<Item = Value> + Unpin, F: FnMut(&BatchReport) {
+        if self.batch_size == 0 || self.batch_bytes == 0 || self.concurrency == 0 {
+            return Err(SolrError::InvalidInput(
+                "`batch_size`, `batch_bytes` and `concurrency` of a bulk indexer must not be zero".into()));
+        }
+
+        let started = Instant::now();
+        let state = BatcherState { docs, batch: Batch::new(0), deadline: None, done: false };
+        let outcomes = stream::unfold(state, move |state| self.next_batch(state))
+            .map(|item| match item {
+                Batched::Batch(batch) => Either::Left(self.send_batch(batch)),
+                Batched::Rejected(doc, error) => Either::Right(future::ready(Outcome::Rejected(doc, error))),
+            })
+            .buffer_unordered(self.concurrency);
+        futures::pin_mut!(outcomes);
+
+        let mut report = BulkReport::default();
+        while let Some(outcome) = outcomes.next().await {
+            match outcome {
+                Outcome::Sent(mut batch_report, batch) => {
+                    report.batches += 1;
+                    report.retries += (batch_report.attempts - 1) as usize;
+                    on_batch(&batch_report);
+                    match batch_report.error.take() {
+                        None => {
+                            report.docs_indexed += batch_report.docs;
+                            report.bytes += batch_report.bytes;
+                        },
+                        Some(error) => {
+                            report.docs_failed += batch_report.docs;
+                            report.failures.push(BatchFailure { index: Some(batch.index), docs: batch.docs, error });
+                        },
+                    }
+                },
+                Outcome::Rejected(doc, error) => {
+                    report.docs_failed += 1;
+                    report.failures.push(BatchFailure { index: None, docs: vec![doc], error });
+                },
+            }
+        }
+        report.elapsed = started.elapsed();
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            collection = %self.collection.name,
+            batches = report.batches,
+            docs_indexed = report.docs_indexed,
+            docs_failed = report.docs_failed,
+            "Bulk indexing finished");
+        Ok(report)
+    }
+
+    /// Takes documents from the input until a batch is full, the flush interval elapses or the
+    /// input ends. A rejected document is yielded on its own, keeping the pending batch.
+    async fn next_batch<S>(&self, mut state: BatcherState<S>) -> Option<(Batched, BatcherState<S>)>
+        where S: Stream<Item = Value> + Unpin {
+        let mut carry = None;
+        while !state.done && state.batch.members.len() < self.batch_size {
+            let next = match state.deadline {
+                Some(deadline) => match tokio::time::timeout_at(deadline, state.docs.next()).await {
+                    Ok(next) => next,
+                    Err(_) => break,
+                },
+                None => state.docs.next().await,
+            };
+            let doc = match next {
+                Some(doc) => doc,
+                None => {
+                    state.done = true;
+                    break;
+                },
+            };
+            if let Err(error) = nested::validate_document(&doc) {
+                return Some((Batched::Rejected(doc, error), state));
+            }
+            let member = match UpdateCommand::Add(doc.clone()).to_member() {
+                Ok(member) => member,
+                Err(error) => return Some((Batched::Rejected(doc, error), state)),
+            };
+            if !state.batch.members.is_empty() && state.batch.bytes + member.len() > self.batch_bytes {
+                carry = Some((member, doc));
+                break;
+            }
+            state.batch.push(member, doc);
+            if state.deadline.is_none() {
+                state.deadline = self.flush_interval.map(|i| tokio::time::Instant::now() + i);
+            }
+        }
+
+        // The flush interval starts only with the first document, so an empty batch means that
+        // the input has ended.
+        if state.batch.members.is_empty() {
+            return None;
+        }
+        let next = Batch::new(state.batch.index + 1);
+        let batch = std::mem::replace(&mut state.batch, next);
+        state.deadline = None;
+        if let Some((member, doc)) = carry {
+            state.batch.push(member, doc);
+            state.deadline = self.flush_interval.map(|i| tokio::time::Instant::now() + i);
+        }
+        Some((Batched::Batch(batch), state))
+    }
+
+    /// Sends a batch, retrying it on recoverable errors.
+    async fn send_batch(&self, batch: Batch) -> Outcome {
+        let started = Instant::now();
+        let path = format!("{}/update{}", self.collection.name, self.commit_options.to_query_string());
+        let body = format!("{{{}}}", batch.members.join(","));
+        let bytes = body.len();
+        let mut attempts = 0;
+        let error = loop {
+            attempts += 1;
+            match self.collection.client.post_raw(&path, body.clone(), &self.options).await {
+                Ok(_) => break None,
+                Err(e) => {
+                    if attempts > self.max_retries || !is_retryable(&e) {
+                        break Some(e);
+                    }
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(
+                        collection = %self.collection.name,
+                        batch = batch.index,
+                        attempts,
+                        error = %e,
+                        "Retrying a failed batch");
+                    tokio::time::delay_for(retry_delay(self.retry_backoff, attempts)).await;
+                },
+            }
+        };
+        let report = BatchReport {
+            index: batch.index,
+            docs: batch.members.len(),
+            bytes,
+            attempts,
+            elapsed: started.elapsed(),
+            error,
+        };
+        Outcome::Sent(report, batch)
+    }
+}
+
+/// Returns the delay before the next attempt of a batch, i.e. the backoff doubled with every retry
+/// and capped at `MAX_RETRY_BACKOFF`.
+///
+/// # Arguments
+/// * `backoff` - The delay before the first retry.
+/// * `attempts` - The number of failed attempts (at least `1`).
+fn retry_delay(backoff: Duration, attempts: u32) -> Duration {
+    2u32.checked_pow(attempts.saturating_sub(1))
+        .and_then(|factor| backoff.checked_mul(factor))
+        .map_or(MAX_RETRY_BACKOFF, |delay| delay.min(MAX_RETRY_BACKOFF))
+}
+
+/// Returns whether a request which failed with the error could succeed if repeated.
+fn is_retryable(error: &SolrError) -> bool {
+    match error {
+        SolrError::Transport { .. } => true,
+        SolrError::Http { status, .. } | SolrError::Solr { status, .. } => *status >= 500 || *status == 429,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::Solr;
+
+    /// Returns ids of the documents of each batch, or `None` for a rejected document.
+    async fn batches(indexer: &BulkIndexer<'_, '_>, docs: Vec<Value>) -> Vec<Option<Vec<String>>> {
+        let state = BatcherState { docs: stream::iter(docs), batch: Batch::new(0), deadline: None, done: false };
+        stream::unfold(state, |state| indexer.next_batch(state))
+            .map(|item| match item {
+                Batched::Batch(batch) => {
+                    assert_eq!(batch.members.len(), batch.docs.len());
+                    Some(batch.docs.iter().map(|doc| doc["id"].as_str().unwrap_or_default().to_string()).collect())
+                },
+                Batched::Rejected(_, _) => None,
+            })
+            .collect()
+            .await
+    }
+
+    fn ids(ids: &[&str]) -> Option<Vec<String>> {
+        Some(ids.iter().map(|id| id.to_string()).collect())
+    }
+
+    #[tokio::test]
+    async fn next_batch_splits_and_flushes() {
+        let solr = Solr::client("http".into(), "localhost".into(), 8983);
+        let users = Collection::new(&solr, "users".into());
+        let docs = (1..=5).map(|i| json!({ "id": i.to_string() })).collect::<Vec<_>>();
+
+        let mut indexer = users.bulk_indexer();
+        indexer.batch_size(2);
+        assert_eq!(batches(&indexer, docs.clone()).await,
+            vec![ids(&["1", "2"]), ids(&["3", "4"]), ids(&["5"])]);
+
+        // The document which overflows a batch starts the next one
+        let member = UpdateCommand::Add(json!({ "id": "1" })).to_member().unwrap();
+        let mut indexer = users.bulk_indexer();
+        indexer.batch_bytes(2 * member.len() + 1);
+        assert_eq!(batches(&indexer, docs.clone()).await,
+            vec![ids(&["1", "2"]), ids(&["3", "4"]), ids(&["5"])]);
+
+        // A rejected document does not flush the pending batch
+        let mut docs = docs;
+        docs.insert(1, json!("not a document"));
+        let mut indexer = users.bulk_indexer();
+        indexer.batch_size(3);
+        assert_eq!(batches(&indexer, docs).await,
+            vec![None, ids(&["1", "2", "3"]), ids(&["4", "5"])]);
+    }
+
+    #[test]
+    fn retryable_errors() {
+        let http = |status| SolrError::Http { url: "http://localhost".into(), status, body: String::new() };
+        assert!(is_retryable(&http(500)));
+        assert!(is_retryable(&http(503)));
+        assert!(is_retryable(&http(429)));
+        assert!(!is_retryable(&http(400)));
+        assert!(!is_retryable(&http(404)));
+        assert!(!is_retryable(&SolrError::VersionConflict { url: "http://localhost".into(), msg: String::new() }));
+        assert!(!is_retryable(&SolrError::InvalidInput(String::new())));
+    }
+
+    #[tokio::test]
+    async fn transport_errors_are_retryable() {
+        // Nothing listens on the port, so the connection is refused
+        let solr = Solr::client("http".into(), "127.0.0.1".into(), 1);
+        let error = solr.get("admin/info/system").await.unwrap_err();
+        assert!(matches!(error, SolrError::Transport { .. }));
+        assert!(is_retryable(&error));
+    }
+
+    #[test]
+    fn retry_delay_doubles_up_to_the_maximum() {
+        let backoff = Duration::from_millis(500);
+        assert_eq!(retry_delay(backoff, 1), backoff);
+        assert_eq!(retry_delay(backoff, 2), Duration::from_secs(1));
+        assert_eq!(retry_delay(backoff, 4), Duration::from_secs(4));
+        assert_eq!(retry_delay(backoff, 10), MAX_RETRY_BACKOFF);
+        assert_eq!(retry_delay(backoff, 40), MAX_RETRY_BACKOFF);
+        assert_eq!(retry_delay(Duration::from_secs(u64::MAX), 2), MAX_RETRY_BACKOFF);
+    }
+}
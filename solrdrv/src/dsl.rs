@@ -0,0 +1,516 @@
+//! A typed query DSL rendering escaped Lucene (standard query parser) syntax.
+//!
+//! Expressions are built with the constructors of `Expr` and rendered using `Display`, so they can
+//! be used both as the main query (`Query::query_expr`) and as filter queries.
+//!
+//! # Example
+//! ```no_run
+//! use std::ops::Bound;
+//! use solrdrv::dsl::Expr;
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+//! # let mut products = solr.collections().get("products".into()).await?;
+//! let expr = Expr::bool()
+//!     .must(&Expr::phrase("name", "usb cable"))
+//!     .should(Expr::term("brand", "acme").boost(2.0))
+//!     .must_not(&Expr::exists("discontinued"))
+//!     .filter(&Expr::range("price", Bound::Included(10), Bound::Unbounded))
+//!     .clone();
+//! // => {!lucene q.op=OR v='+name:"usb cable" (brand:acme)^2 -discontinued:* +filter(price:[10 TO *])'}
+//! let found = products.search()
+//!     .query_expr(&expr)
+//!     .fq(Expr::prefix("sku", "A-").to_string())
+//!     .commit().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # See
+//! https://lucene.apache.org/solr/guide/8_5/the-standard-query-parser.html
+
+use std::fmt;
+use std::ops::Bound;
+use serde_json::Value;
+use crate::{local_param_value, SolrError};
+
+/// Escapes characters with a special meaning in the standard query parser (including whitespace)
+/// with a backslash.
+///
+/// # Arguments
+/// * `value` -
+pub fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c.is_whitespace() || "\\+-!():^[]\"{}~*?|&/".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Escapes a value like `escape`, except for the wildcards `*` and `?`.
+fn escape_wildcard(pattern: &str) -> String {
+    let mut escaped = String::with_capacity(pattern.len());
+    for c in pattern.chars() {
+        if c.is_whitespace() || "\\+-!():^[]\"{}~|&/".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Escapes a single term, rendering an empty term as an empty phrase.
+fn escape_term(term: &str) -> String {
+    if term.is_empty() {
+        return "\"\"".into();
+    }
+    escape(term)
+}
+
+/// An occurrence of a clause of a boolean expression
+enum Occur {
+    Must,
+    Should,
+    MustNot,
+    Filter,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A query expression
+pub enum Expr {
+    /// Matches all documents (`*:*`).
+    All,
+    /// Matches documents containing a term in a field.
+    Term {
+        /// The field name.
+        field: String,
+        /// The term.
+        value: String,
+    },
+    /// Matches documents containing a phrase, optionally within a slop (proximity search).
+    Phrase {
+        /// The field name.
+        field: String,
+        /// The phrase.
+        text: String,
+        /// The maximum number of positions between the terms of the phrase.
+        slop: Option<u32>,
+    },
+    /// Matches documents with a value of a field in a range.
+    Range {
+        /// The field name.
+        field: String,
+        /// The lower bound.
+        lower: Bound<String>,
+        /// The upper bound.
+        upper: Bound<String>,
+    },
+    /// Matches documents containing a term matching a pattern with wildcards `*` and `?`.
+    Wildcard {
+        /// The field name.
+        field: String,
+        /// The pattern.
+        pattern: String,
+    },
+    /// Matches documents containing a term starting with a prefix.
+    Prefix {
+        /// The field name.
+        field: String,
+        /// The prefix.
+        prefix: String,
+    },
+    /// Matches documents containing a term similar to a term.
+    Fuzzy {
+        /// The field name.
+        field: String,
+        /// The term.
+        term: String,
+        /// The maximum edit distance (0 to 2).
+        distance: u8,
+    },
+    /// Matches documents with any value of a field.
+    Exists {
+        /// The field name.
+        field: String,
+    },
+    /// Multiplies the score of an expression.
+    Boost {
+        /// The boosted expression.
+        expr: Box<Expr>,
+        /// The boost.
+        boost: f64,
+    },
+    /// Sets the score of all documents matching an expression to a constant.
+    ConstantScore {
+        /// The expression.
+        expr: Box<Expr>,
+        /// The score.
+        score: f64,
+    },
+    /// Combines expressions. A boolean expression without clauses matches all documents.
+    Bool {
+        /// The expressions which must match.
+        must: Vec<Expr>,
+        /// The expressions which should match (optional if there is a `must` or `filter` clause).
+        should: Vec<Expr>,
+        /// The expressions which must not match.
+        must_not: Vec<Expr>,
+        /// The expressions which must match, without influencing the score.
+        filter: Vec<Expr>,
+    },
+    /// A query of another query parser using local params, e.g. `{!term f=id v='a b'}`.
+    LocalParams {
+        /// The query parser.
+        parser: String,
+        /// The local params.
+        params: Vec<(String, String)>,
+        /// The query value (the `v` local param).
+        value: String,
+    },
+}
+
+impl Expr {
+    /// Creates an expression matching all documents.
+    pub fn all() -> Expr {
+        Expr::All
+    }
+
+    /// Creates a term expression, e.g. `field:value`.
+    ///
+    /// # Arguments
+    /// * `field` -
+    /// * `value` -
+    pub fn term<T>(field: &str, value: T) -> Expr
+        where T: std::string::ToString {
+        Expr::Term { field: field.to_string(), value: value.to_string() }
+    }
+
+    /// Creates a phrase expression, e.g. `field:"some text"`.
+    ///
+    /// # Arguments
+    /// * `field` -
+    /// * `text` -
+    pub fn phrase(field: &str, text: &str) -> Expr {
+        Expr::Phrase { field: field.to_string(), text: text.to_string(), slop: None }
+    }
+
+    /// Creates a proximity expression, e.g. `field:"some text"~2`.
+    ///
+    /// # Arguments
+    /// * `field` -
+    /// * `text` -
+    /// * `slop` - The maximum number of positions between the terms.
+    pub fn proximity(field: &str, text: &str, slop: u32) -> Expr {
+        Expr::Phrase { field: field.to_string(), text: text.to_string(), slop: Some(slop) }
+    }
+
+    /// Creates a range expression, e.g. `field:[10 TO *]`.
+    ///
+    /// # Arguments
+    /// * `field` -
+    /// * `lower` -
+    /// * `upper` -
+    pub fn range<T>(field: &str, lower: Bound<T>, upper: Bound<T>) -> Expr
+        where T: std::string::ToString {
+        let bound = |bound: Bound<T>| match bound {
+            Bound::Included(value) => Bound::Included(value.to_string()),
+            Bound::Excluded(value) => Bound::Excluded(value.to_string()),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        Expr::Range { field: field.to_string(), lower: bound(lower), upper: bound(upper) }
+    }
+
+    /// Creates a wildcard expression, e.g. `field:te?t*`. Other special characters of the
+    /// pattern are escaped.
+    ///
+    /// # Arguments
+    /// * `field` -
+    /// * `pattern` -
+    pub fn wildcard(field: &str, pattern: &str) -> Expr {
+        Expr::Wildcard { field: field.to_string(), pattern: pattern.to_string() }
+    }
+
+    /// Creates a prefix expression, e.g. `field:pre*`.
+    ///
+    /// # Arguments
+    /// * `field` -
+    /// * `prefix` -
+    pub fn prefix(field: &str, prefix: &str) -> Expr {
+        Expr::Prefix { field: field.to_string(), prefix: prefix.to_string() }
+    }
+
+    /// Creates a fuzzy expression, e.g. `field:term~1`.
+    ///
+    /// # Arguments
+    /// * `field` -
+    /// * `term` -
+    /// * `distance` - The maximum edit distance, Solr supports 0 to 2.
+    pub fn fuzzy(field: &str, term: &str, distance: u8) -> Expr {
+        Expr::Fuzzy { field: field.to_string(), term: term.to_string(), distance }
+    }
+
+    /// Creates an expression matching documents with any value of a field, i.e. `field:*`.
+    ///
+    /// # Arguments
+    /// * `field` -
+    pub fn exists(field: &str) -> Expr {
+        Expr::Exists { field: field.to_string() }
+    }
+
+    /// Creates a boolean expression without clauses.
+    pub fn bool() -> Expr {
+        Expr::Bool { must: vec![], should: vec![], must_not: vec![], filter: vec![] }
+    }
+
+    /// Creates a query of another query parser, e.g. `{!parent which='type:order' v='sku:A-42'}`.
+    ///
+    /// # Arguments
+    /// * `parser` - The query parser, e.g. `term` or `parent`.
+    /// * `params` - The local params.
+    /// * `value` - The query value.
+    pub fn local_params(parser: &str, params: &[(&str, &str)], value: &str) -> Expr {
+        Expr::LocalParams {
+            parser: parser.to_string(),
+            params: params.iter().map(|(p, v)| (p.to_string(), v.to_string())).collect(),
+            value: value.to_string(),
+        }
+    }
+
+    /// Multiplies the score of the expression, e.g. `(field:value)^2`.
+    ///
+    /// # Arguments
+    /// * `boost` -
+    pub fn boost(&mut self, boost: f64) -> &mut Self {
+        let expr = Box::new(std::mem::replace(self, Expr::All));
+        *self = Expr::Boost { expr, boost };
+        self
+    }
+
+    /// Sets the score of matching documents to a constant, e.g. `(field:value)^=1`.
+    ///
+    /// # Arguments
+    /// * `score` -
+    pub fn constant_score(&mut self, score: f64) -> &mut Self {
+        let expr = Box::new(std::mem::replace(self, Expr::All));
+        *self = Expr::ConstantScore { expr, score };
+        self
+    }
+
+    /// Adds a clause to the boolean expression. Other expressions become the first `must`
+    /// clause of a new boolean expression.
+    fn add_clause(&mut self, occur: Occur, expr: &Expr) -> &mut Self {
+        if let Expr::Bool { must, should, must_not, filter } = self {
+            match occur {
+                Occur::Must => must.push(expr.clone()),
+                Occur::Should => should.push(expr.clone()),
+                Occur::MustNot => must_not.push(expr.clone()),
+                Occur::Filter => filter.push(expr.clone()),
+            }
+            return self;
+        }
+        let first = std::mem::replace(self, Expr::bool());
+        self.add_clause(Occur::Must, &first).add_clause(occur, expr)
+    }
+
+    /// Adds a clause which must match.
+    ///
+    /// # Arguments
+    /// * `expr` -
+    pub fn must(&mut self, expr: &Expr) -> &mut Self {
+        self.add_clause(Occur::Must, expr)
+    }
+
+    /// Adds a clause which should match. Clauses which should match are rendered with explicit `OR`s
+    /// (and `q.op=OR` next to required clauses), so they do not depend on the default operator.
+    ///
+    /// # Arguments
+    /// * `expr` -
+    pub fn should(&mut self, expr: &Expr) -> &mut Self {
+        self.add_clause(Occur::Should, expr)
+    }
+
+    /// Adds a clause which must not match.
+    ///
+    /// # Arguments
+    /// * `expr` -
+    pub fn must_not(&mut self, expr: &Expr) -> &mut Self {
+        self.add_clause(Occur::MustNot, expr)
+    }
+
+    /// Adds a clause which must match, without influencing the score (and cached in the filter
+    /// cache).
+    ///
+    /// # Arguments
+    /// * `expr` -
+    pub fn filter(&mut self, expr: &Expr) -> &mut Self {
+        self.add_clause(Occur::Filter, expr)
+    }
+
+    /// Creates an expression from a JSON-encoded query (see `Query::query_json`).
+    ///
+    /// # Arguments
+    /// * `json` -
+    pub(crate) fn from_json(json: &Value) -> Result<Expr, SolrError> {
+        if let Some(field) = json.get("field") {
+            let field = match field.as_str() {
+                Some(f) => f,
+                None => return Err(SolrError::InvalidInput(
+                    format!("expected `field` to be a string, got `{}`", field))),
+            };
+            return match json.get("value") {
+                Some(Value::String(value)) => Ok(Expr::phrase(field, value)),
+                Some(value @ Value::Number(_)) | Some(value @ Value::Bool(_)) => Ok(Expr::term(field, value)),
+                Some(value) => Err(SolrError::InvalidInput(
+                    format!("expected `value` of field `{}` to be a string, a number or a boolean, got `{}`", field, value))),
+                None => Err(SolrError::InvalidInput(
+                    format!("missing `value` of field `{}`", field))),
+            };
+        }
+
+        for op_name in ["and", "or"].iter() {
+            if let Some(op) = json.get(op_name) {
+                let ops = match op.as_array() {
+                    Some(ops) => ops,
+                    None => return Err(SolrError::InvalidInput(
+                        format!("expected `{}` to be an array, got `{}`", op_name, op))),
+                };
+                let mut expr = Expr::bool();
+                for op in ops {
+                    let op = Expr::from_json(op)?;
+                    if *op_name == "and" {
+                        expr.must(&op);
+                    } else {
+                        expr.should(&op);
+                    }
+                }
+                return Ok(expr);
+            }
+        }
+
+        if let Some(op) = json.get("neg") {
+            return Ok(Expr::bool().must_not(&Expr::from_json(op)?).clone());
+        }
+
+        Err(SolrError::InvalidInput(
+            format!("expected an operation or a field, got `{}`", json)))
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::All => write!(f, "*:*"),
+            Expr::Term { field, value } => write!(f, "{}:{}", escape(field), escape_term(value)),
+            Expr::Phrase { field, text, slop } => {
+                write!(f, "{}:\"{}\"", escape(field), text.replace('\\', "\\\\").replace('"', "\\\""))?;
+                match slop {
+                    Some(slop) => write!(f, "~{}", slop),
+                    None => Ok(()),
+                }
+            },
+            Expr::Range { field, lower, upper } => {
+                let (open, lower) = match lower {
+                    Bound::Included(value) => ('[', escape_term(value)),
+                    Bound::Excluded(value) => ('{', escape_term(value)),
+                    Bound::Unbounded => ('[', "*".into()),
+                };
+                let (close, upper) = match upper {
+                    Bound::Included(value) => (']', escape_term(value)),
+                    Bound::Excluded(value) => ('}', escape_term(value)),
+                    Bound::Unbounded => (']', "*".into()),
+                };
+                write!(f, "{}:{}{} TO {}{}", escape(field), open, lower, upper, close)
+            },
+            Expr::Wildcard { field, pattern } => write!(f, "{}:{}", escape(field), escape_wildcard(pattern)),
+            Expr::Prefix { field, prefix } => write!(f, "{}:{}*", escape(field), escape(prefix)),
+            Expr::Fuzzy { field, term, distance } => write!(f, "{}:{}~{}", escape(field), escape_term(term), distance),
+            Expr::Exists { field } => write!(f, "{}:*", escape(field)),
+            Expr::Boost { expr, boost } => write!(f, "({})^{}", expr, boost),
+            Expr::ConstantScore { expr, score } => write!(f, "({})^={}", expr, score),
+            Expr::Bool { must, should, must_not, filter } => {
+                if must_not.is_empty() && filter.is_empty() && must.len() + should.len() <= 1 {
+                    // A single clause (or none) does not need a boolean query
+                    return match must.first().or_else(|| should.first()) {
+                        Some(expr) => write!(f, "{}", expr),
+                        None => write!(f, "*:*"),
+                    };
+                }
+                let mut clauses = vec![];
+                // A purely negative query matches nothing unless it is combined with all documents
+                if must.is_empty() && should.is_empty() && filter.is_empty() {
+                    clauses.push("*:*".to_string());
+                }
+                clauses.extend(must.iter().map(|expr| format!("+{}", expr)));
+                // Explicit ORs keep the clauses optional whatever the default operator (`q.op`)
+                if !should.is_empty() {
+                    clauses.push(should.iter().map(Expr::to_string).collect::<Vec<_>>().join(" OR "));
+                }
+                clauses.extend(must_not.iter().map(|expr| format!("-{}", expr)));
+                clauses.extend(filter.iter().map(|expr| format!("+filter({})", expr)));
+                if !should.is_empty() && (!must.is_empty() || !filter.is_empty()) {
+                    // A lone optional clause next to required ones would still become required with
+                    // `q.op=AND`, so the operator is set explicitly
+                    return write!(f, "{{!lucene q.op=OR v={}}}", local_param_value(&clauses.join(" ")));
+                }
+                write!(f, "({})", clauses.join(" "))
+            },
+            Expr::LocalParams { parser, params, value } => {
+                write!(f, "{{!{}", parser)?;
+                for (param, param_value) in params {
+                    write!(f, " {}={}", param, local_param_value(param_value))?;
+                }
+                write!(f, " v={}}}", local_param_value(value))
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn query_dsl_rendering() {
+        assert_eq!(escape("a+b (c) d:e/f"), "a\\+b\\ \\(c\\)\\ d\\:e\\/f");
+        assert_eq!(Expr::term("id", "a b").to_string(), "id:a\\ b");
+        assert_eq!(Expr::term("id", "").to_string(), "id:\"\"");
+        assert_eq!(Expr::proximity("name", "say \"hi\"", 2).to_string(), "name:\"say \\\"hi\\\"\"~2");
+        assert_eq!(Expr::range("price", Bound::Excluded(-1), Bound::Unbounded).to_string(), "price:{\\-1 TO *]");
+        assert_eq!(Expr::wildcard("sku", "A-?1*").to_string(), "sku:A\\-?1*");
+        assert_eq!(Expr::prefix("sku", "A*").to_string(), "sku:A\\**");
+        assert_eq!(Expr::fuzzy("name", "roam", 1).to_string(), "name:roam~1");
+        assert_eq!(Expr::exists("email").to_string(), "email:*");
+        assert_eq!(Expr::local_params("term", &[("f", "id")], "a b").to_string(), "{!term f=id v='a b'}");
+
+        let expr = Expr::bool()
+            .must(&Expr::phrase("name", "usb cable"))
+            .should(Expr::term("brand", "acme").boost(2.0))
+            .must_not(&Expr::exists("discontinued"))
+            .filter(&Expr::range("price", Bound::Included(10), Bound::Unbounded))
+            .clone();
+        assert_eq!(expr.to_string(),
+            "{!lucene q.op=OR v='+name:\"usb cable\" (brand:acme)^2 -discontinued:* +filter(price:[10 TO *])'}");
+        let expr = Expr::bool().must(&Expr::term("a", 1)).should(&Expr::term("b", 2)).should(&Expr::term("c", 3)).clone();
+        assert_eq!(expr.to_string(), "{!lucene q.op=OR v='+a:1 b:2 OR c:3'}");
+        let expr = Expr::bool().should(&Expr::term("b", 2)).should(&Expr::term("c", 3)).must_not(&Expr::term("d", 4)).clone();
+        assert_eq!(expr.to_string(), "(b:2 OR c:3 -d:4)");
+        assert_eq!(Expr::bool().to_string(), "*:*");
+        assert_eq!(Expr::bool().must(&Expr::all()).to_string(), "*:*");
+        assert_eq!(Expr::bool().must_not(&Expr::term("a", 1)).to_string(), "(*:* -a:1)");
+        assert_eq!(Expr::term("a", 1).must(&Expr::term("b", 2)).constant_score(1.5).to_string(), "((+a:1 +b:2))^=1.5");
+
+        let json = json!({ "or": [
+            { "neg": { "and": [{ "field": "name", "value": "Some (One)" }, { "field": "age", "value": 19 }] } },
+            { "field": "age", "value": 21 }
+        ]});
+        assert_eq!(Expr::from_json(&json).unwrap().to_string(), "((*:* -(+name:\"Some (One)\" +age:19)) OR age:21)");
+        assert!(Expr::from_json(&json!({ "field": "age" })).is_err());
+        assert!(Expr::from_json(&json!({ "field": "age", "value": [1] })).is_err());
+        assert!(Expr::from_json(&json!({ "and": {} })).is_err());
+        assert!(Expr::from_json(&json!({ "foo": 1 })).is_err());
+    }
+}
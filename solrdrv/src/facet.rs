@@ -0,0 +1,574 @@
+//! Builders and results of faceting (the `facet.*` parameters).
+//!
+//! # Example
+//! ```no_run
+//! use solrdrv::facet::{FieldFacet, RangeFacet, FacetSort};
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+//! # let mut products = solr.collections().get("products".into()).await?;
+//! let response = products.search()
+//!     .query("*:*")
+//!     .facet_field(FieldFacet::new("brand").limit(5).mincount(1).sort(FacetSort::Count))
+//!     .facet_range(RangeFacet::new("price", 0, 1000, 100).other("after"))
+//!     .facet_query("in_stock:true")
+//!     .facet_pivot("category,brand")
+//!     .execute().await?;
+//!
+//! let facets = response.facet_counts.unwrap();
+//! for brand in &facets.facet_fields["brand"] {
+//!     println!("{:?}: {}", brand.value, brand.count);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # See
+//! https://lucene.apache.org/solr/guide/8_5/faceting.html
+
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use crate::serde_helpers::format_date;
+use crate::{local_param_value, SolrError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// An order of facet values
+pub enum FacetSort {
+    /// Sorts by count, highest first.
+    Count,
+    /// Sorts by value in index order (lexicographically).
+    Index,
+}
+
+impl FacetSort {
+    /// Returns the value of the `facet.sort` parameter.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FacetSort::Count => "count",
+            FacetSort::Index => "index",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// An algorithm used to compute field facets
+pub enum FacetMethod {
+    /// Enumerates all terms of the field, suitable for fields with few distinct values.
+    Enum,
+    /// Uses the field cache, suitable for fields with many distinct values.
+    Fc,
+    /// Uses a per-segment field cache, suitable for frequently changing indexes.
+    Fcs,
+}
+
+impl FacetMethod {
+    /// Returns the value of the `facet.method` parameter.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FacetMethod::Enum => "enum",
+            FacetMethod::Fc => "fc",
+            FacetMethod::Fcs => "fcs",
+        }
+    }
+}
+
+/// Returns a facet parameter value with its local params, e.g. `{!key=top facet.limit=5}brand`.
+/// Options are sent as local params rather than per-field `f.<field>.facet.*` parameters, so
+/// several facets of the same field do not share them.
+fn facet_value(field: &str, key: &Option<String>, exclude: &[String], options: &[(String, String)]) -> String {
+    let mut params = vec![];
+    if let Some(key) = key {
+        params.push(format!("key={}", local_param_value(key)));
+    }
+    if !exclude.is_empty() {
+        params.push(format!("ex={}", local_param_value(&exclude.join(","))));
+    }
+    params.extend(options.iter().map(|(p, v)| format!("{}={}", p, local_param_value(v))));
+    if params.is_empty() {
+        return field.to_string();
+    }
+    format!("{{!{}}}{}", params.join(" "), field)
+}
+
+#[derive(Debug, Clone)]
+/// A builder of a field facet, counting documents per distinct value of a field
+pub struct FieldFacet {
+    field: String,
+    key: Option<String>,
+    exclude: Vec<String>,
+    params: Vec<(String, String)>,
+}
+
+impl FieldFacet {
+    /// Creates a new field facet.
+    ///
+    /// # Arguments
+    /// * `field` - The field name.
+    pub fn new(field: &str) -> FieldFacet {
+        FieldFacet {
+            field: field.to_string(),
+            key: None,
+            exclude: vec![],
+            params: vec![],
+        }
+    }
+
+    fn set<T>(&mut self, param: &str, value: T) -> &mut Self
+        where T: std::string::ToString {
+        let param = format!("facet.{}", param);
+        self.params.retain(|(p, _)| *p != param);
+        self.params.push((param, value.to_string()));
+        self
+    }
+
+    /// Sets the name under which the facet is returned (defaults to the field name). Required to
+    /// add several facets of the same field.
+    ///
+    /// # Arguments
+    /// * `key` -
+    pub fn key(&mut self, key: &str) -> &mut Self {
+        self.key = Some(key.to_string());
+        self
+    }
+
+    /// Excludes filter queries tagged with `tag` (`{!tag=...}`) when counting, e.g. for
+    /// multi-select faceting.
+    ///
+    /// # Arguments
+    /// * `tag` -
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/faceting.html#tagging-and-excluding-filters
+    pub fn exclude(&mut self, tag: &str) -> &mut Self {
+        self.exclude.push(tag.to_string());
+        self
+    }
+
+    /// Returns only values starting with the prefix.
+    ///
+    /// # Arguments
+    /// * `prefix` -
+    pub fn prefix(&mut self, prefix: &str) -> &mut Self {
+        self.set("prefix", prefix)
+    }
+
+    /// Sets the maximum number of returned values. Solr defaults to 100, use `-1` for all values.
+    ///
+    /// # Arguments
+    /// * `limit` -
+    pub fn limit(&mut self, limit: i64) -> &mut Self {
+        self.set("limit", limit)
+    }
+
+    /// Sets the offset of the first returned value, used for paging.
+    ///
+    /// # Arguments
+    /// * `offset` -
+    pub fn offset(&mut self, offset: usize) -> &mut Self {
+        self.set("offset", offset)
+    }
+
+    /// Sets the minimum count of a returned value.
+    ///
+    /// # Arguments
+    /// * `mincount` -
+    pub fn mincount(&mut self, mincount: u64) -> &mut Self {
+        self.set("mincount", mincount)
+    }
+
+    /// Sets the order of returned values.
+    ///
+    /// # Arguments
+    /// * `sort` -
+    pub fn sort(&mut self, sort: FacetSort) -> &mut Self {
+        self.set("sort", sort.as_str())
+    }
+
+    /// Sets whether to also return the number of documents without a value (returned with value
+    /// `None`).
+    ///
+    /// # Arguments
+    /// * `missing` -
+    pub fn missing(&mut self, missing: bool) -> &mut Self {
+        self.set("missing", missing)
+    }
+
+    /// Sets the algorithm used to compute the facet.
+    ///
+    /// # Arguments
+    /// * `method` -
+    pub fn method(&mut self, method: FacetMethod) -> &mut Self {
+        self.set("method", method.as_str())
+    }
+
+    pub(crate) fn to_params(&self) -> Vec<(String, String)> {
+        vec![("facet.field".to_string(), facet_value(&self.field, &self.key, &self.exclude, &self.params))]
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A builder of a range facet, counting documents per range of a numeric or date field
+pub struct RangeFacet {
+    field: String,
+    key: Option<String>,
+    exclude: Vec<String>,
+    params: Vec<(String, String)>,
+}
+
+impl RangeFacet {
+    /// Creates a new range facet over a numeric field.
+    ///
+    /// # Arguments
+    /// * `field` - The field name.
+    /// * `start` - The lower bound of the first range.
+    /// * `end` - The upper bound of the last range.
+    /// * `gap` - The size of each range.
+    pub fn new<T>(field: &str, start: T, end: T, gap: T) -> RangeFacet
+        where T: std::string::ToString {
+        let mut facet = RangeFacet {
+            field: field.to_string(),
+            key: None,
+            exclude: vec![],
+            params: vec![],
+        };
+        facet.set("start", start)
+            .set("end", end)
+            .set("gap", gap);
+        facet
+    }
+
+    /// Creates a new range facet over a date field.
+    ///
+    /// # Arguments
+    /// * `field` - The field name.
+    /// * `start` - The lower bound of the first range.
+    /// * `end` - The upper bound of the last range.
+    /// * `gap` - The size of each range in the date math syntax (e.g. `+1DAY`).
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/working-with-dates.html#date-math
+    pub fn dates(field: &str, start: &DateTime<Utc>, end: &DateTime<Utc>, gap: &str) -> RangeFacet {
+        RangeFacet::date_math(field, &format_date(start), &format_date(end), gap)
+    }
+
+    /// Creates a new range facet over a date field with bounds in the date math syntax (e.g.
+    /// `NOW/DAY-30DAYS`).
+    ///
+    /// # Arguments
+    /// * `field` - The field name.
+    /// * `start` - The lower bound of the first range.
+    /// * `end` - The upper bound of the last range.
+    /// * `gap` - The size of each range (e.g. `+1DAY`).
+    pub fn date_math(field: &str, start: &str, end: &str, gap: &str) -> RangeFacet {
+        RangeFacet::new(field, start, end, gap)
+    }
+
+    fn set<T>(&mut self, param: &str, value: T) -> &mut Self
+        where T: std::string::ToString {
+        let param = format!("facet.range.{}", param);
+        self.params.retain(|(p, _)| *p != param);
+        self.params.push((param, value.to_string()));
+        self
+    }
+
+    fn add(&mut self, param: &str, value: &str) -> &mut Self {
+        self.params.push((format!("facet.range.{}", param), value.to_string()));
+        self
+    }
+
+    /// Sets the name under which the facet is returned (defaults to the field name). Required to
+    /// add several range facets of the same field.
+    ///
+    /// # Arguments
+    /// * `key` -
+    pub fn key(&mut self, key: &str) -> &mut Self {
+        self.key = Some(key.to_string());
+        self
+    }
+
+    /// Excludes filter queries tagged with `tag` when counting.
+    ///
+    /// # Arguments
+    /// * `tag` -
+    pub fn exclude(&mut self, tag: &str) -> &mut Self {
+        self.exclude.push(tag.to_string());
+        self
+    }
+
+    /// Sets whether the last range is truncated at `end` if `gap` does not divide the interval.
+    ///
+    /// # Arguments
+    /// * `hardend` -
+    pub fn hardend(&mut self, hardend: bool) -> &mut Self {
+        self.set("hardend", hardend)
+    }
+
+    /// Also counts documents `before` the first range, `after` the last range or `between` the
+    /// bounds (or `all` of them). Can be set multiple times.
+    ///
+    /// # Arguments
+    /// * `other` -
+    pub fn other(&mut self, other: &str) -> &mut Self {
+        self.add("other", other)
+    }
+
+    /// Sets which bounds are inclusive (`lower`, `upper`, `edge`, `outer` or `all`). Can be set
+    /// multiple times.
+    ///
+    /// # Arguments
+    /// * `include` -
+    pub fn include(&mut self, include: &str) -> &mut Self {
+        self.add("include", include)
+    }
+
+    /// Sets the minimum count of a returned range.
+    ///
+    /// # Arguments
+    /// * `mincount` -
+    pub fn mincount(&mut self, mincount: u64) -> &mut Self {
+        self.params.retain(|(p, _)| p != "facet.mincount");
+        self.params.push(("facet.mincount".into(), mincount.to_string()));
+        self
+    }
+
+    pub(crate) fn to_params(&self) -> Vec<(String, String)> {
+        vec![("facet.range".to_string(), facet_value(&self.field, &self.key, &self.exclude, &self.params))]
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A builder of an interval facet, counting documents per arbitrary (possibly overlapping)
+/// interval of a field
+pub struct IntervalFacet {
+    field: String,
+    key: Option<String>,
+    exclude: Vec<String>,
+    intervals: Vec<String>,
+}
+
+impl IntervalFacet {
+    /// Creates a new interval facet.
+    ///
+    /// # Arguments
+    /// * `field` - The field name.
+    pub fn new(field: &str) -> IntervalFacet {
+        IntervalFacet {
+            field: field.to_string(),
+            key: None,
+            exclude: vec![],
+            intervals: vec![],
+        }
+    }
+
+    /// Sets the name under which the facet is returned (defaults to the field name). Required to
+    /// add several interval facets of the same field.
+    ///
+    /// # Arguments
+    /// * `key` -
+    pub fn key(&mut self, key: &str) -> &mut Self {
+        self.key = Some(key.to_string());
+        self
+    }
+
+    /// Excludes filter queries tagged with `tag` when counting.
+    ///
+    /// # Arguments
+    /// * `tag` -
+    pub fn exclude(&mut self, tag: &str) -> &mut Self {
+        self.exclude.push(tag.to_string());
+        self
+    }
+
+    /// Adds an interval, e.g. `[0,10)` or `[*,NOW]`.
+    ///
+    /// # Arguments
+    /// * `interval` -
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/faceting.html#interval-faceting
+    pub fn interval(&mut self, interval: &str) -> &mut Self {
+        self.intervals.push(interval.to_string());
+        self
+    }
+
+    /// Adds an interval returned under the specified name.
+    ///
+    /// # Arguments
+    /// * `key` - The name of the interval.
+    /// * `interval` -
+    pub fn keyed_interval(&mut self, key: &str, interval: &str) -> &mut Self {
+        self.intervals.push(format!("{{!key={}}}{}", local_param_value(key), interval));
+        self
+    }
+
+    pub(crate) fn to_params(&self) -> Vec<(String, String)> {
+        let intervals = self.intervals.iter()
+            .map(|interval| ("facet.interval.set".to_string(), interval.clone()))
+            .collect::<Vec<_>>();
+        vec![("facet.interval".to_string(), facet_value(&self.field, &self.key, &self.exclude, &intervals))]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A count of documents with a facet value
+pub struct FacetCount {
+    /// The value, or `None` for the count of documents without a value (see
+    /// `FieldFacet::missing`).
+    pub value: Option<String>,
+    /// The number of documents.
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Counts of a range facet
+pub struct RangeFacetCounts {
+    /// The counts of the ranges, keyed by their lower bounds.
+    pub counts: Vec<FacetCount>,
+    /// The size of each range.
+    pub gap: Value,
+    /// The lower bound of the first range.
+    pub start: Value,
+    /// The upper bound of the last range.
+    pub end: Value,
+    /// The number of documents before the first range (see `RangeFacet::other`).
+    pub before: Option<u64>,
+    /// The number of documents after the last range.
+    pub after: Option<u64>,
+    /// The number of documents between `start` and `end`.
+    pub between: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+/// A count of a pivot facet value, with counts of values of the next pivot field
+pub struct PivotFacet {
+    /// The field name.
+    pub field: String,
+    /// The value.
+    pub value: Value,
+    /// The number of documents.
+    pub count: u64,
+    /// The counts of values of the next pivot field.
+    #[serde(default)]
+    pub pivot: Vec<PivotFacet>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// Parsed `facet_counts` section of a query response
+pub struct FacetCounts {
+    /// The counts of facet queries, keyed by the query (or its `key` local param).
+    pub facet_queries: HashMap<String, u64>,
+    /// The counts of field facets, keyed by the field name (or the facet key).
+    pub facet_fields: HashMap<String, Vec<FacetCount>>,
+    /// The counts of range facets, keyed by the field name (or the facet key).
+    pub facet_ranges: HashMap<String, RangeFacetCounts>,
+    /// The counts of pivot facets, keyed by the comma separated field names.
+    pub facet_pivot: HashMap<String, Vec<PivotFacet>>,
+    /// The counts of interval facets, keyed by the field name (or the facet key).
+    pub facet_intervals: HashMap<String, Vec<FacetCount>>,
+}
+
+impl FacetCounts {
+    /// Parses the `facet_counts` section.
+    ///
+    /// # Arguments
+    /// * `url` - The URL of the request, used in errors.
+    /// * `section` - The section JSON.
+    pub(crate) fn parse(url: &str, section: &Value) -> Result<FacetCounts, SolrError> {
+        let unexpected = |name: &str| SolrError::UnexpectedResponse {
+            url: url.to_string(),
+            message: format!("malformed `facet_counts.{}`", name),
+        };
+        let entries = |name: &str| section.get(name)
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten();
+
+        let mut counts = FacetCounts::default();
+        for (key, count) in entries("facet_queries") {
+            counts.facet_queries.insert(key.clone(), count.as_u64().ok_or_else(|| unexpected("facet_queries"))?);
+        }
+        for (key, list) in entries("facet_fields") {
+            counts.facet_fields.insert(key.clone(), parse_counts(list).ok_or_else(|| unexpected("facet_fields"))?);
+        }
+        for (key, range) in entries("facet_ranges") {
+            let range_counts = RangeFacetCounts {
+                counts: parse_counts(&range["counts"]).ok_or_else(|| unexpected("facet_ranges"))?,
+                gap: range["gap"].clone(),
+                start: range["start"].clone(),
+                end: range["end"].clone(),
+                before: range["before"].as_u64(),
+                after: range["after"].as_u64(),
+                between: range["between"].as_u64(),
+            };
+            counts.facet_ranges.insert(key.clone(), range_counts);
+        }
+        for (key, pivot) in entries("facet_pivot") {
+            let pivot = serde_json::from_value(pivot.clone()).map_err(|_| unexpected("facet_pivot"))?;
+            counts.facet_pivot.insert(key.clone(), pivot);
+        }
+        for (key, intervals) in entries("facet_intervals") {
+            counts.facet_intervals.insert(key.clone(), parse_counts(intervals).ok_or_else(|| unexpected("facet_intervals"))?);
+        }
+        Ok(counts)
+    }
+}
+
+/// Parses a named list of counts in any of the `json.nl` formats (`flat`, `map`, `arrarr`).
+fn parse_counts(list: &Value) -> Option<Vec<FacetCount>> {
+    let value = |v: &Value| match v {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        v => Some(v.to_string()),
+    };
+    match list {
+        Value::Array(items) if items.iter().all(Value::is_array) => items.iter()
+            .map(|pair| Some(FacetCount { value: value(pair.get(0)?), count: pair.get(1)?.as_u64()? }))
+            .collect(),
+        Value::Array(items) if items.len() % 2 == 0 => items.chunks(2)
+            .map(|pair| Some(FacetCount { value: value(&pair[0]), count: pair[1].as_u64()? }))
+            .collect(),
+        Value::Object(items) => items.iter()
+            .map(|(v, count)| Some(FacetCount { value: Some(v.clone()), count: count.as_u64()? }))
+            .collect(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn facet_params_and_counts() {
+        let params = FieldFacet::new("brand").key("b").exclude("brand").limit(5).limit(10).to_params();
+        assert_eq!(params, vec![
+            ("facet.field".to_string(), "{!key=b ex=brand facet.limit=10}brand".to_string()),
+        ]);
+        let params = RangeFacet::new("price", 0, 100, 10).other("before").other("after").mincount(1).to_params();
+        assert_eq!(params, vec![("facet.range".to_string(),
+            "{!facet.range.start=0 facet.range.end=100 facet.range.gap=10 facet.range.other=before facet.range.other=after facet.mincount=1}price".to_string())]);
+        let params = IntervalFacet::new("price").key("cheap").interval("[0,10)").keyed_interval("mid", "[10,100]").to_params();
+        assert_eq!(params, vec![("facet.interval".to_string(),
+            "{!key=cheap facet.interval.set=[0,10) facet.interval.set='{!key=mid}[10,100]'}price".to_string())]);
+
+        let section = json!({
+            "facet_queries": { "in_stock:true": 7 },
+            "facet_fields": { "brand": ["acme", 5, "globex", 2, null, 1] },
+            "facet_ranges": { "price": { "counts": ["0", 3, "100", 4], "gap": 100, "start": 0, "end": 200, "after": 1 } },
+            "facet_pivot": { "category,brand": [{ "field": "category", "value": "tools", "count": 4,
+                "pivot": [{ "field": "brand", "value": "acme", "count": 3 }] }] },
+            "facet_intervals": { "price": { "[0,10)": 2 } }
+        });
+        let counts = FacetCounts::parse("http://localhost", &section).unwrap();
+        assert_eq!(counts.facet_queries["in_stock:true"], 7);
+        assert_eq!(counts.facet_fields["brand"][1], FacetCount { value: Some("globex".into()), count: 2 });
+        assert_eq!(counts.facet_fields["brand"][2].value, None);
+        assert_eq!(counts.facet_ranges["price"].counts.len(), 2);
+        assert_eq!(counts.facet_ranges["price"].after, Some(1));
+        assert_eq!(counts.facet_pivot["category,brand"][0].pivot[0].count, 3);
+        assert_eq!(counts.facet_intervals["price"][0].count, 2);
+    }
+}
@@ -0,0 +1,424 @@
+//! Result grouping (`group=true`) and field collapsing (`{!collapse}` with `expand=true`).
+//!
+//! # Example
+//! ```no_run
+//! use solrdrv::group::{Collapse, Expand, Grouping};
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+//! # let mut variants = solr.collections().get("variants".into()).await?;
+//! // One group per product, with up to 3 variants each
+//! let response = variants.search()
+//!     .query("color:red")
+//!     .group(Grouping::new().field("product_id").limit(3).ngroups(true))
+//!     .execute().await?;
+//! let products = &response.grouped.unwrap()["product_id"];
+//!
+//! // The cheapest variant per product, with the other variants expanded
+//! let response = variants.search()
+//!     .query("color:red")
+//!     .collapse(Collapse::new("product_id").min("price"))
+//!     .expand(Expand::new().rows(5))
+//!     .execute().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # See
+//! https://lucene.apache.org/solr/guide/8_5/result-grouping.html
+
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use crate::{local_param_value, SolrError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A format of grouped results
+pub enum GroupFormat {
+    /// Returns the groups with their document lists.
+    Grouped,
+    /// Returns the grouped documents in a single flat document list.
+    Simple,
+}
+
+impl GroupFormat {
+    /// Returns the value of the `group.format` parameter.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GroupFormat::Grouped => "grouped",
+            GroupFormat::Simple => "simple",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// A builder of result grouping parameters
+pub struct Grouping {
+    params: Vec<(String, String)>,
+}
+
+impl Grouping {
+    /// Creates new grouping parameters. At least one of `field`, `query` or `func` must be set.
+    pub fn new() -> Grouping {
+        Grouping::default()
+    }
+
+    fn set<T>(&mut self, param: &str, value: T) -> &mut Self
+        where T: std::string::ToString {
+        let param = format!("group.{}", param);
+        self.params.retain(|(p, _)| *p != param);
+        self.params.push((param, value.to_string()));
+        self
+    }
+
+    fn add<T>(&mut self, param: &str, value: T) -> &mut Self
+        where T: std::string::ToString {
+        self.params.push((format!("group.{}", param), value.to_string()));
+        self
+    }
+
+    /// Groups documents by values of a field. Can be set multiple times.
+    ///
+    /// # Arguments
+    /// * `field` -
+    pub fn field(&mut self, field: &str) -> &mut Self {
+        self.add("field", field)
+    }
+
+    /// Adds a group of documents matching a query. Can be set multiple times.
+    ///
+    /// # Arguments
+    /// * `query` -
+    pub fn query(&mut self, query: &str) -> &mut Self {
+        self.add("query", query)
+    }
+
+    /// Groups documents by values of a function query. Can be set multiple times.
+    ///
+    /// # Arguments
+    /// * `func` -
+    pub fn func(&mut self, func: &str) -> &mut Self {
+        self.add("func", func)
+    }
+
+    /// Sets the maximum number of documents returned per group. Solr defaults to 1.
+    ///
+    /// # Arguments
+    /// * `limit` -
+    pub fn limit(&mut self, limit: i64) -> &mut Self {
+        self.set("limit", limit)
+    }
+
+    /// Sets the offset of the first document returned per group.
+    ///
+    /// # Arguments
+    /// * `offset` -
+    pub fn offset(&mut self, offset: usize) -> &mut Self {
+        self.set("offset", offset)
+    }
+
+    /// Sets the order of documents within each group. The order of groups is given by `sort`.
+    ///
+    /// # Arguments
+    /// * `sort` -
+    pub fn sort(&mut self, sort: &str) -> &mut Self {
+        self.set("sort", sort)
+    }
+
+    /// Sets whether to return the number of matching groups (see `GroupResult::ngroups`).
+    ///
+    /// # Arguments
+    /// * `ngroups` -
+    pub fn ngroups(&mut self, ngroups: bool) -> &mut Self {
+        self.set("ngroups", ngroups)
+    }
+
+    /// Sets the format of grouped results.
+    ///
+    /// # Arguments
+    /// * `format` -
+    pub fn format(&mut self, format: GroupFormat) -> &mut Self {
+        self.set("format", format.as_str())
+    }
+
+    /// Sets whether to return the grouped documents in the main result (`QueryResponse::docs`)
+    /// instead of the `grouped` section.
+    ///
+    /// # Arguments
+    /// * `main` -
+    pub fn main(&mut self, main: bool) -> &mut Self {
+        self.set("main", main)
+    }
+
+    /// Sets whether facets are computed over groups instead of documents.
+    ///
+    /// # Arguments
+    /// * `facet` -
+    pub fn facet(&mut self, facet: bool) -> &mut Self {
+        self.set("facet", facet)
+    }
+
+    pub(crate) fn to_params(&self) -> Vec<(String, String)> {
+        self.params.clone()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A handling of documents without a value of the collapse field
+pub enum NullPolicy {
+    /// Removes the documents.
+    Ignore,
+    /// Keeps each document as its own group.
+    Expand,
+    /// Collapses the documents into a single group.
+    Collapse,
+}
+
+impl NullPolicy {
+    /// Returns the value of the `nullPolicy` local param.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NullPolicy::Ignore => "ignore",
+            NullPolicy::Expand => "expand",
+            NullPolicy::Collapse => "collapse",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A builder of the collapsing query parser, keeping a single head document per field value
+pub struct Collapse {
+    params: Vec<(String, String)>,
+}
+
+impl Collapse {
+    /// Creates a new collapse of documents by values of a single-valued field. By default, the
+    /// document with the highest score is kept.
+    ///
+    /// # Arguments
+    /// * `field` -
+    pub fn new(field: &str) -> Collapse {
+        Collapse { params: vec![("field".into(), field.into())] }
+    }
+
+    fn set(&mut self, param: &str, value: &str) -> &mut Self {
+        self.params.retain(|(p, _)| p != param);
+        self.params.push((param.to_string(), value.to_string()));
+        self
+    }
+
+    /// Keeps the document with the minimum value of a numeric field or function.
+    ///
+    /// # Arguments
+    /// * `field` -
+    pub fn min(&mut self, field: &str) -> &mut Self {
+        self.head("min", field)
+    }
+
+    /// Keeps the document with the maximum value of a numeric field or function.
+    ///
+    /// # Arguments
+    /// * `field` -
+    pub fn max(&mut self, field: &str) -> &mut Self {
+        self.head("max", field)
+    }
+
+    /// Keeps the first document according to a sort, e.g. `price asc, id desc`.
+    ///
+    /// # Arguments
+    /// * `sort` -
+    pub fn sort(&mut self, sort: &str) -> &mut Self {
+        self.head("sort", sort)
+    }
+
+    fn head(&mut self, selector: &str, value: &str) -> &mut Self {
+        // Only one head selector can be used
+        self.params.retain(|(p, _)| p != "min" && p != "max" && p != "sort");
+        self.set(selector, value)
+    }
+
+    /// Sets the handling of documents without a value.
+    ///
+    /// # Arguments
+    /// * `null_policy` -
+    pub fn null_policy(&mut self, null_policy: NullPolicy) -> &mut Self {
+        self.set("nullPolicy", null_policy.as_str())
+    }
+
+    /// Sets a hint of the collapse implementation, e.g. `top_fc`.
+    ///
+    /// # Arguments
+    /// * `hint` -
+    pub fn hint(&mut self, hint: &str) -> &mut Self {
+        self.set("hint", hint)
+    }
+
+    /// Builds the filter query.
+    ///
+    /// # Example
+    /// ```no_run
+    /// let fq = solrdrv::group::Collapse::new("product_id").sort("price asc").build();
+    /// // => {!collapse field=product_id sort='price asc'}
+    /// ```
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/collapse-and-expand-results.html#collapsing-query-parser
+    pub fn build(&self) -> String {
+        let params = self.params.iter()
+            .map(|(p, v)| format!("{}={}", p, local_param_value(v)))
+            .collect::<Vec<_>>();
+        format!("{{!collapse {}}}", params.join(" "))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// A builder of expand parameters, returning the documents collapsed into each head document
+pub struct Expand {
+    params: Vec<(String, String)>,
+}
+
+impl Expand {
+    /// Creates new expand parameters.
+    pub fn new() -> Expand {
+        Expand::default()
+    }
+
+    fn set<T>(&mut self, param: &str, value: T) -> &mut Self
+        where T: std::string::ToString {
+        let param = format!("expand.{}", param);
+        self.params.retain(|(p, _)| *p != param);
+        self.params.push((param, value.to_string()));
+        self
+    }
+
+    fn add<T>(&mut self, param: &str, value: T) -> &mut Self
+        where T: std::string::ToString {
+        self.params.push((format!("expand.{}", param), value.to_string()));
+        self
+    }
+
+    /// Sets the maximum number of expanded documents per group. Solr defaults to 5.
+    ///
+    /// # Arguments
+    /// * `rows` -
+    pub fn rows(&mut self, rows: usize) -> &mut Self {
+        self.set("rows", rows)
+    }
+
+    /// Sets the order of expanded documents. Solr defaults to `score desc`.
+    ///
+    /// # Arguments
+    /// * `sort` -
+    pub fn sort(&mut self, sort: &str) -> &mut Self {
+        self.set("sort", sort)
+    }
+
+    /// Sets the query of expanded documents. Defaults to the main query.
+    ///
+    /// # Arguments
+    /// * `query` -
+    pub fn query(&mut self, query: &str) -> &mut Self {
+        self.set("q", query)
+    }
+
+    /// Adds a filter query of expanded documents. Can be set multiple times. Defaults to the main
+    /// filter queries.
+    ///
+    /// # Arguments
+    /// * `fq` -
+    pub fn fq(&mut self, fq: &str) -> &mut Self {
+        self.add("fq", fq)
+    }
+
+    pub(crate) fn to_params(&self) -> Vec<(String, String)> {
+        self.params.clone()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+/// A list of documents of a group
+pub struct DocList {
+    /// The number of documents in the group.
+    #[serde(rename = "numFound")]
+    pub num_found: u64,
+    /// The offset of the first returned document.
+    #[serde(default)]
+    pub start: u64,
+    /// The maximum score of the documents, if scores were requested.
+    #[serde(rename = "maxScore")]
+    pub max_score: Option<f64>,
+    /// The returned documents.
+    pub docs: Vec<Value>,
+}
+
+impl DocList {
+    /// Deserializes the returned documents into type `T`.
+    pub fn docs_as<T>(&self) -> Result<Vec<T>, SolrError>
+        where T: DeserializeOwned {
+        self.docs.iter()
+            .map(|doc| T::deserialize(doc).map_err(SolrError::from))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+/// A group of documents sharing a value
+pub struct Group {
+    /// The shared value (`null` for documents without a value).
+    #[serde(rename = "groupValue")]
+    pub group_value: Value,
+    /// The documents of the group.
+    pub doclist: DocList,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+/// Grouped results of a single `group.field`, `group.func` or `group.query`
+pub struct GroupResult {
+    /// The number of documents matching the query.
+    pub matches: u64,
+    /// The number of groups (see `Grouping::ngroups`).
+    pub ngroups: Option<u64>,
+    /// The groups (`GroupFormat::Grouped` and a field or function).
+    #[serde(default)]
+    pub groups: Vec<Group>,
+    /// The documents (`GroupFormat::Simple` or a query).
+    pub doclist: Option<DocList>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::QueryResponse;
+
+    #[test]
+    fn grouped_and_expanded_response() {
+        let to_params = |pairs: &[(&str, &str)]| pairs.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(Grouping::new().field("a").field("b").limit(2).limit(3).sort("x asc").sort("y asc").to_params(),
+            to_params(&[("group.field", "a"), ("group.field", "b"), ("group.limit", "3"), ("group.sort", "y asc")]));
+        assert_eq!(Expand::new().rows(1).rows(2).fq("a:1").fq("b:2").to_params(),
+            to_params(&[("expand.rows", "2"), ("expand.fq", "a:1"), ("expand.fq", "b:2")]));
+        assert_eq!(Collapse::new("product_id").min("price").sort("price asc, id desc").build(),
+            "{!collapse field=product_id sort='price asc, id desc'}");
+
+        let res = json!({
+            "grouped": {
+                "product_id": {
+                    "matches": 5,
+                    "ngroups": 2,
+                    "groups": [{ "groupValue": "p1", "doclist": { "numFound": 3, "start": 0, "docs": [{ "id": "1" }] } }]
+                },
+                "color:red": { "matches": 5, "doclist": { "numFound": 1, "start": 0, "docs": [{ "id": "2" }] } }
+            },
+            "expanded": { "p1": { "numFound": 2, "start": 0, "docs": [{ "id": "3" }, { "id": "4" }] } }
+        });
+        let response = QueryResponse::parse("http://localhost", res).unwrap();
+        let grouped = response.grouped.unwrap();
+        assert_eq!(grouped["product_id"].ngroups, Some(2));
+        assert_eq!(grouped["product_id"].groups[0].group_value, json!("p1"));
+        assert_eq!(grouped["color:red"].doclist.as_ref().unwrap().num_found, 1);
+        assert_eq!(response.expanded.unwrap()["p1"].docs.len(), 2);
+    }
+}
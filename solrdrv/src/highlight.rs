@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use serde_json::Value;
+
+/// Highlighted snippets of a single document, keyed by field name.
+pub type Snippets = HashMap<String, Vec<String>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// An implementation of highlighting
+pub enum HighlightMethod {
+    /// The unified highlighter, the fastest and most flexible one.
+    Unified,
+    /// The original highlighter, supporting the most options.
+    Original,
+    /// The fast vector highlighter, requiring term vectors with positions and offsets.
+    FastVector,
+}
+
+impl HighlightMethod {
+    /// Returns the value of the `hl.method` parameter.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HighlightMethod::Unified => "unified",
+            HighlightMethod::Original => "original",
+            HighlightMethod::FastVector => "fastVector",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// A builder of highlighting parameters
+pub struct Highlight {
+    params: Vec<(String, String)>,
+}
+
+impl Highlight {
+    /// Creates new highlighting parameters, highlighting fields listed in `fl` by default.
+    pub fn new() -> Highlight {
+        Highlight::default()
+    }
+
+    /// Sets a highlighting parameter (without the `hl.` prefix).
+    ///
+    /// # Arguments
+    /// * `param` - The parameter name, e.g. `maxAnalyzedChars`.
+    /// * `value` - The parameter value.
+    pub fn set<T>(&mut self, param: &str, value: T) -> &mut Self
+        where T: std::string::ToString {
+        let param = format!("hl.{}", param);
+        self.params.retain(|(p, _)| *p != param);
+        self.params.push((param, value.to_string()));
+        self
+    }
+
+    /// Sets the fields to highlight (comma separated, wildcards allowed).
+    ///
+    /// # Arguments
+    /// * `fields` -
+    pub fn fields(&mut self, fields: &str) -> &mut Self {
+        self.set("fl", fields)
+    }
+
+    /// Sets the highlighter implementation.
+    ///
+    /// # Arguments
+    /// * `method` -
+    pub fn method(&mut self, method: HighlightMethod) -> &mut Self {
+        self.set("method", method.as_str())
+    }
+
+    /// Sets the maximum number of snippets per field. Solr defaults to 1.
+    ///
+    /// # Arguments
+    /// * `snippets` -
+    pub fn snippets(&mut self, snippets: usize) -> &mut Self {
+        self.set("snippets", snippets)
+    }
+
+    /// Sets the approximate size of a snippet in characters, `0` for the whole field value.
+    ///
+    /// # Arguments
+    /// * `fragsize` -
+    pub fn fragsize(&mut self, fragsize: usize) -> &mut Self {
+        self.set("fragsize", fragsize)
+    }
+
+    /// Sets the tags surrounding highlighted terms. Defaults to `<em>` and `</em>`.
+    ///
+    /// # Arguments
+    /// * `pre` -
+    /// * `post` -
+    pub fn tags(&mut self, pre: &str, post: &str) -> &mut Self {
+        // The original highlighter uses its own parameters
+        self.set("tag.pre", pre)
+            .set("tag.post", post)
+            .set("simple.pre", pre)
+            .set("simple.post", post)
+    }
+
+    /// Sets a query used for highlighting instead of `q`.
+    ///
+    /// # Arguments
+    /// * `query` -
+    pub fn query(&mut self, query: &str) -> &mut Self {
+        self.set("q", query)
+    }
+
+    /// Sets whether to highlight only terms matching the query in the same field.
+    ///
+    /// # Arguments
+    /// * `require_field_match` -
+    pub fn require_field_match(&mut self, require_field_match: bool) -> &mut Self {
+        self.set("requireFieldMatch", require_field_match)
+    }
+
+    /// Sets the encoder of snippets, `html` escapes HTML in field values.
+    ///
+    /// # Arguments
+    /// * `encoder` -
+    pub fn encoder(&mut self, encoder: &str) -> &mut Self {
+        self.set("encoder", encoder)
+    }
+
+    pub(crate) fn to_params(&self) -> Vec<(String, String)> {
+        self.params.clone()
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A returned document with its highlighted snippets
+pub struct Highlighted<T = Value> {
+    /// The document.
+    pub doc: T,
+    /// The snippets of the document, keyed by field name. Empty if nothing was highlighted.
+    pub snippets: Snippets,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlight_params() {
+        let params = Highlight::new().fields("body").snippets(3).tags("<b>", "</b>").to_params();
+        assert_eq!(params, vec![
+            ("hl.fl".to_string(), "body".to_string()),
+            ("hl.snippets".to_string(), "3".to_string()),
+            ("hl.tag.pre".to_string(), "<b>".to_string()),
+            ("hl.tag.post".to_string(), "</b>".to_string()),
+            ("hl.simple.pre".to_string(), "<b>".to_string()),
+            ("hl.simple.post".to_string(), "</b>".to_string()),
+        ]);
+    }
+}
@@ -0,0 +1,480 @@
+//! Builders and results of the JSON Facet API (the `json.facet` parameter).
+//!
+//! # Example
+//! ```no_run
+//! use solrdrv::json_facet::{self, JsonFacet};
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+//! # let mut products = solr.collections().get("products".into()).await?;
+//! let response = products.search()
+//!     .query("*:*")
+//!     .json_facet("categories", JsonFacet::terms("category")
+//!         .limit(10)
+//!         .sort("avg_price desc")
+//!         .stat("avg_price", &json_facet::avg("price"))
+//!         .facet("brands", JsonFacet::terms("brand").limit(3)))
+//!     .json_facet_stat("median_price", &json_facet::percentile("price", &[50.0]))
+//!     .execute().await?;
+//!
+//! let facets = response.facets.unwrap();
+//! for category in &facets.facets["categories"].buckets {
+//!     println!("{}: {} ({:?})", category.val.as_ref().unwrap(), category.count, category.stats["avg_price"]);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # See
+//! https://lucene.apache.org/solr/guide/8_5/json-facet-api.html
+
+use std::collections::HashMap;
+use serde_json::{json, Map, Value};
+use crate::SolrError;
+
+/// Returns the average of a numeric field or function.
+///
+/// # Arguments
+/// * `field` -
+pub fn avg(field: &str) -> String {
+    format!("avg({})", field)
+}
+
+/// Returns the sum of a numeric field or function.
+///
+/// # Arguments
+/// * `field` -
+pub fn sum(field: &str) -> String {
+    format!("sum({})", field)
+}
+
+/// Returns the minimum of a field or function.
+///
+/// # Arguments
+/// * `field` -
+pub fn min(field: &str) -> String {
+    format!("min({})", field)
+}
+
+/// Returns the maximum of a field or function.
+///
+/// # Arguments
+/// * `field` -
+pub fn max(field: &str) -> String {
+    format!("max({})", field)
+}
+
+/// Returns the exact number of unique values of a field.
+///
+/// # Arguments
+/// * `field` -
+pub fn unique(field: &str) -> String {
+    format!("unique({})", field)
+}
+
+/// Returns the estimated number of unique values of a field (HyperLogLog).
+///
+/// # Arguments
+/// * `field` -
+pub fn hll(field: &str) -> String {
+    format!("hll({})", field)
+}
+
+/// Returns percentiles of a numeric field or function.
+///
+/// # Arguments
+/// * `field` -
+/// * `percentiles` - Percentiles between 0 and 100, e.g. `&[50.0, 90.0, 99.0]`.
+pub fn percentile(field: &str, percentiles: &[f64]) -> String {
+    let percentiles = percentiles.iter().map(f64::to_string).collect::<Vec<_>>();
+    format!("percentile({},{})", field, percentiles.join(","))
+}
+
+/// Returns the standard deviation of a numeric field or function.
+///
+/// # Arguments
+/// * `field` -
+pub fn stddev(field: &str) -> String {
+    format!("stddev({})", field)
+}
+
+#[derive(Debug, Clone)]
+/// A builder of a JSON facet (terms, range, query or heatmap), possibly with nested sub-facets
+/// and stat aggregations
+pub struct JsonFacet {
+    props: Map<String, Value>,
+}
+
+impl JsonFacet {
+    fn new(facet_type: &str) -> JsonFacet {
+        let mut props = Map::new();
+        props.insert("type".into(), json!(facet_type));
+        JsonFacet { props }
+    }
+
+    /// Creates a terms facet, bucketing documents by values of a field.
+    ///
+    /// # Arguments
+    /// * `field` -
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/json-facet-api.html#terms-facet
+    pub fn terms(field: &str) -> JsonFacet {
+        let mut facet = JsonFacet::new("terms");
+        facet.set("field", field);
+        facet
+    }
+
+    /// Creates a range facet, bucketing documents by ranges of a numeric or date field.
+    ///
+    /// # Arguments
+    /// * `field` -
+    /// * `start` - The lower bound of the first range.
+    /// * `end` - The upper bound of the last range.
+    /// * `gap` - The size of each range (a number, or date math such as `+1DAY`).
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/json-facet-api.html#range-facet
+    pub fn range<T>(field: &str, start: T, end: T, gap: T) -> JsonFacet
+        where T: serde::ser::Serialize {
+        let mut facet = JsonFacet::new("range");
+        facet.set("field", field)
+            .set("start", start)
+            .set("end", end)
+            .set("gap", gap);
+        facet
+    }
+
+    /// Creates a query facet, a single bucket of documents matching a query.
+    ///
+    /// # Arguments
+    /// * `query` -
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/json-facet-api.html#query-facet
+    pub fn query(query: &str) -> JsonFacet {
+        let mut facet = JsonFacet::new("query");
+        facet.set("q", query);
+        facet
+    }
+
+    /// Creates a heatmap facet over a spatial field. The result is returned raw in
+    /// `Bucket::stats`.
+    ///
+    /// # Arguments
+    /// * `field` -
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/json-facet-api.html#heatmap-facet
+    pub fn heatmap(field: &str) -> JsonFacet {
+        let mut facet = JsonFacet::new("heatmap");
+        facet.set("field", field);
+        facet
+    }
+
+    /// Sets a facet property.
+    ///
+    /// # Arguments
+    /// * `prop` - The property name.
+    /// * `value` - The property value.
+    pub fn set<T>(&mut self, prop: &str, value: T) -> &mut Self
+        where T: serde::ser::Serialize {
+        self.props.insert(prop.to_string(), json!(value));
+        self
+    }
+
+    /// Sets the maximum number of returned buckets (terms facets). Solr defaults to 10, use `-1`
+    /// for all buckets.
+    ///
+    /// # Arguments
+    /// * `limit` -
+    pub fn limit(&mut self, limit: i64) -> &mut Self {
+        self.set("limit", limit)
+    }
+
+    /// Sets the offset of the first returned bucket (terms facets).
+    ///
+    /// # Arguments
+    /// * `offset` -
+    pub fn offset(&mut self, offset: usize) -> &mut Self {
+        self.set("offset", offset)
+    }
+
+    /// Sets the minimum count of a returned bucket.
+    ///
+    /// # Arguments
+    /// * `mincount` -
+    pub fn mincount(&mut self, mincount: u64) -> &mut Self {
+        self.set("mincount", mincount)
+    }
+
+    /// Sets the order of buckets (terms facets), e.g. `count desc`, `index asc` or a stat name
+    /// such as `avg_price desc`.
+    ///
+    /// # Arguments
+    /// * `sort` -
+    pub fn sort(&mut self, sort: &str) -> &mut Self {
+        self.set("sort", sort)
+    }
+
+    /// Returns only buckets with values starting with the prefix (terms facets).
+    ///
+    /// # Arguments
+    /// * `prefix` -
+    pub fn prefix(&mut self, prefix: &str) -> &mut Self {
+        self.set("prefix", prefix)
+    }
+
+    /// Sets whether to also return a bucket of documents without a value (see `Buckets::missing`).
+    ///
+    /// # Arguments
+    /// * `missing` -
+    pub fn missing(&mut self, missing: bool) -> &mut Self {
+        self.set("missing", missing)
+    }
+
+    /// Sets whether to return the total number of buckets (see `Buckets::num_buckets`).
+    ///
+    /// # Arguments
+    /// * `num_buckets` -
+    pub fn num_buckets(&mut self, num_buckets: bool) -> &mut Self {
+        self.set("numBuckets", num_buckets)
+    }
+
+    /// Sets whether to return a bucket of all documents with a value (see `Buckets::all_buckets`).
+    ///
+    /// # Arguments
+    /// * `all_buckets` -
+    pub fn all_buckets(&mut self, all_buckets: bool) -> &mut Self {
+        self.set("allBuckets", all_buckets)
+    }
+
+    /// Also counts documents `before` the first range, `after` the last range or `between` the
+    /// bounds, or `all` of them (range facets).
+    ///
+    /// # Arguments
+    /// * `other` -
+    pub fn other(&mut self, other: &str) -> &mut Self {
+        self.set("other", other)
+    }
+
+    /// Sets which range bounds are inclusive (range facets).
+    ///
+    /// # Arguments
+    /// * `include` -
+    pub fn include(&mut self, include: &str) -> &mut Self {
+        self.set("include", include)
+    }
+
+    /// Sets whether the last range is truncated at `end` (range facets).
+    ///
+    /// # Arguments
+    /// * `hardend` -
+    pub fn hardend(&mut self, hardend: bool) -> &mut Self {
+        self.set("hardend", hardend)
+    }
+
+    /// Sets the region of a heatmap facet, e.g. `["-180 -90" TO "180 90"]`.
+    ///
+    /// # Arguments
+    /// * `geom` -
+    pub fn geom(&mut self, geom: &str) -> &mut Self {
+        self.set("geom", geom)
+    }
+
+    /// Sets the grid level of a heatmap facet.
+    ///
+    /// # Arguments
+    /// * `grid_level` -
+    pub fn grid_level(&mut self, grid_level: u32) -> &mut Self {
+        self.set("gridLevel", grid_level)
+    }
+
+    /// Excludes filter queries tagged with `tag` from the facet domain.
+    ///
+    /// # Arguments
+    /// * `tag` -
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/json-faceting-domain-changes.html
+    pub fn exclude_tags(&mut self, tag: &str) -> &mut Self {
+        let domain = self.props.entry("domain").or_insert_with(|| json!({}));
+        match domain["excludeTags"].as_array_mut() {
+            Some(tags) => tags.push(json!(tag)),
+            None => domain["excludeTags"] = json!([tag]),
+        }
+        self
+    }
+
+    /// Restricts the facet domain by a filter query.
+    ///
+    /// # Arguments
+    /// * `filter` -
+    pub fn domain_filter(&mut self, filter: &str) -> &mut Self {
+        let domain = self.props.entry("domain").or_insert_with(|| json!({}));
+        match domain["filter"].as_array_mut() {
+            Some(filters) => filters.push(json!(filter)),
+            None => domain["filter"] = json!([filter]),
+        }
+        self
+    }
+
+    /// Adds a sub-facet computed for each bucket.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the sub-facet in the result.
+    /// * `facet` -
+    pub fn facet(&mut self, name: &str, facet: &JsonFacet) -> &mut Self {
+        self.sub_facet(name, facet.build())
+    }
+
+    /// Adds a stat aggregation computed for each bucket, e.g. `avg(price)`.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the stat in the result.
+    /// * `aggregation` - An aggregation function (see `avg`, `unique`, `percentile`, ...).
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/json-facet-api.html#stat-facet-functions
+    pub fn stat(&mut self, name: &str, aggregation: &str) -> &mut Self {
+        self.sub_facet(name, json!(aggregation))
+    }
+
+    fn sub_facet(&mut self, name: &str, facet: Value) -> &mut Self {
+        let facets = self.props.entry("facet").or_insert_with(|| json!({}));
+        facets[name] = facet;
+        self
+    }
+
+    /// Builds the facet as a JSON object.
+    pub fn build(&self) -> Value {
+        Value::Object(self.props.clone())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// A bucket of the JSON Facet API result; the `facets` section of a response is the root bucket
+pub struct Bucket {
+    /// The value of the bucket (`None` for the root bucket, query facets and special buckets).
+    pub val: Option<Value>,
+    /// The number of documents in the bucket.
+    pub count: u64,
+    /// The stat aggregations (and raw heatmaps) of the bucket, keyed by name.
+    pub stats: HashMap<String, Value>,
+    /// The terms and range sub-facets of the bucket, keyed by name.
+    pub facets: HashMap<String, Buckets>,
+    /// The query sub-facets of the bucket, keyed by name.
+    pub queries: HashMap<String, Bucket>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// Buckets of a terms or range facet
+pub struct Buckets {
+    /// The buckets.
+    pub buckets: Vec<Bucket>,
+    /// The total number of buckets (see `JsonFacet::num_buckets`).
+    pub num_buckets: Option<u64>,
+    /// The bucket of all documents with a value (see `JsonFacet::all_buckets`).
+    pub all_buckets: Option<Box<Bucket>>,
+    /// The bucket of documents without a value (see `JsonFacet::missing`).
+    pub missing: Option<Box<Bucket>>,
+    /// The bucket of documents before the first range (see `JsonFacet::other`).
+    pub before: Option<Box<Bucket>>,
+    /// The bucket of documents after the last range.
+    pub after: Option<Box<Bucket>>,
+    /// The bucket of documents between the range bounds.
+    pub between: Option<Box<Bucket>>,
+}
+
+impl Bucket {
+    /// Parses a bucket (e.g. the `facets` section of a response).
+    ///
+    /// # Arguments
+    /// * `url` - The URL of the request, used in errors.
+    /// * `bucket` - The bucket JSON.
+    pub(crate) fn parse(url: &str, bucket: &Value) -> Result<Bucket, SolrError> {
+        let fields = match bucket.as_object() {
+            Some(fields) => fields,
+            None => return Err(SolrError::UnexpectedResponse {
+                url: url.to_string(),
+                message: format!("expected a facet bucket, got `{}`", bucket),
+            }),
+        };
+        let mut result = Bucket::default();
+        for (name, value) in fields {
+            match (name.as_str(), value) {
+                ("val", _) => result.val = Some(value.clone()),
+                ("count", _) => result.count = value.as_u64().unwrap_or(0),
+                (_, Value::Object(facet)) if facet.contains_key("buckets") => {
+                    let buckets = match &facet["buckets"] {
+                        Value::Array(buckets) => buckets.iter()
+                            .map(|b| Bucket::parse(url, b))
+                            .collect::<Result<Vec<_>, _>>()?,
+                        _ => vec![],
+                    };
+                    let special = |name: &str| match facet.get(name) {
+                        Some(b) => Bucket::parse(url, b).map(|b| Some(Box::new(b))),
+                        None => Ok(None),
+                    };
+                    result.facets.insert(name.clone(), Buckets {
+                        buckets,
+                        num_buckets: facet.get("numBuckets").and_then(Value::as_u64),
+                        all_buckets: special("allBuckets")?,
+                        missing: special("missing")?,
+                        before: special("before")?,
+                        after: special("after")?,
+                        between: special("between")?,
+                    });
+                },
+                (_, Value::Object(facet)) if facet.contains_key("count") => {
+                    result.queries.insert(name.clone(), Bucket::parse(url, value)?);
+                },
+                _ => {
+                    result.stats.insert(name.clone(), value.clone());
+                },
+            }
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn json_facet_build_and_parse() {
+        let facet = JsonFacet::terms("category")
+            .limit(2)
+            .stat("avg_price", &avg("price"))
+            .facet("brands", JsonFacet::terms("brand").exclude_tags("b"))
+            .build();
+        assert_eq!(facet, json!({
+            "type": "terms",
+            "field": "category",
+            "limit": 2,
+            "facet": {
+                "avg_price": "avg(price)",
+                "brands": { "type": "terms", "field": "brand", "domain": { "excludeTags": ["b"] } }
+            }
+        }));
+
+        let facets = Bucket::parse("http://localhost", &json!({
+            "count": 10,
+            "median": 42.5,
+            "cheap": { "count": 3 },
+            "categories": {
+                "numBuckets": 5,
+                "buckets": [{ "val": "tools", "count": 4, "brands": { "buckets": [{ "val": "acme", "count": 2 }] } }]
+            }
+        })).unwrap();
+        assert_eq!(facets.count, 10);
+        assert_eq!(facets.stats["median"], json!(42.5));
+        assert_eq!(facets.queries["cheap"].count, 3);
+        assert_eq!(facets.facets["categories"].num_buckets, Some(5));
+        let tools = &facets.facets["categories"].buckets[0];
+        assert_eq!(tools.val, Some(json!("tools")));
+        assert_eq!(tools.facets["brands"].buckets[0].count, 2);
+    }
+}
@@ -1,7 +1,7 @@
 //! Solrdrv is an unofficial Solr driver for the Rust programming language.
 //!
 //! # Example
-//! ```
+//! ```no_run
 //! extern crate solrdrv;
 //!
 //! use solrdrv::{
@@ -57,38 +57,243 @@
 pub use tokio;
 pub use serde;
 pub use serde_json;
+pub use futures;
+
+pub use chrono;
+
+mod bulk;
+pub mod dsl;
+pub mod facet;
+pub mod group;
+mod highlight;
+pub mod json_facet;
+mod mlt;
+pub mod nested;
+mod realtime;
+mod response;
+pub mod serde_helpers;
+mod spellcheck;
+mod stats;
+mod suggest;
+mod terms;
+mod update;
+
+pub use bulk::{BatchFailure, BatchReport, BulkIndexer, BulkReport};
+pub use facet::{FacetCounts, FieldFacet, IntervalFacet, RangeFacet};
+pub use highlight::{Highlight, HighlightMethod, Highlighted, Snippets};
+pub use json_facet::JsonFacet;
+pub use mlt::{InterestingTerm, InterestingTerms, MltMode, MoreLikeThis, MoreLikeThisResult};
+pub use nested::ChildTransformer;
+pub use realtime::RealTimeGet;
+pub use response::{QueryResponse, ResponseHeader};
+pub use spellcheck::{Alternative, Collation, Spellcheck, SpellcheckResult, Suggestion};
+pub use stats::{FieldStats, Stats, StatsField};
+pub use suggest::{SuggestTerm, Suggester};
+pub use terms::{TermCount, Terms, TermsSort};
+pub use update::{AtomicUpdate, CommitOptions, CommitStrategy, Modifier};
 
 use std::fmt;
 use std::vec::Vec;
 use serde_json::json;
 use serde_json::Value;
 use std::collections::HashMap;
+use std::time::Duration;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use serde::de::DeserializeOwned;
 // use std::error::Error;
 // use serde::{Serialize, Deserialize};
 
 const MAX_CHAR_VAL: u32 = std::char::MAX as u32;
+const CURSOR_DEFAULT_ROWS: usize = 100;
+
+/// The maximum length of a query URL sent using `QueryTransport::Get`. Longer queries are sent
+/// using the JSON Request API instead, as Solr's Jetty rejects request lines longer than 8 KiB by
+/// default.
+pub const MAX_GET_URL_LENGTH: usize = 8000;
+
+/// The name of the field holding a version of a document, used for optimistic concurrency.
+pub const VERSION_FIELD: &str = "_version_";
+
+/// The name of the field holding the unique key of the root document of a nested document block.
+pub const ROOT_FIELD: &str = "_root_";
+
+/// The name of the field holding the path of a nested document within its root document.
+pub const NEST_PATH_FIELD: &str = "_nest_path_";
+
+/// The name of the field holding the unique key of the parent of a nested document.
+pub const NEST_PARENT_FIELD: &str = "_nest_parent_";
+
+/// The name of the field listing anonymous (unlabelled) child documents.
+pub const CHILD_DOCUMENTS_FIELD: &str = "_childDocuments_";
+
+/// Returns a value usable in local params, quoting it if it contains whitespace, quotes or braces.
+pub(crate) fn local_param_value(value: &str) -> String {
+    let plain = !value.is_empty() && !value.chars()
+        .any(|c| c.is_whitespace() || c == '\'' || c == '"' || c == '{' || c == '}' || c == ']');
+    if plain {
+        return value.to_string();
+    }
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
 
 #[derive(Debug)]
 /// A common error type used by this library
-pub struct SolrError;
+pub enum SolrError {
+    /// The request could not be sent or its response could not be read.
+    Transport {
+        /// The URL of the failed request.
+        url: String,
+        /// The underlying HTTP client error.
+        source: reqwest::Error,
+    },
+    /// Solr responded with a non-success HTTP status and without an error payload.
+    Http {
+        /// The URL of the failed request.
+        url: String,
+        /// The HTTP status code of the response.
+        status: u16,
+        /// The raw body of the response.
+        body: String,
+    },
+    /// Solr responded with an `error` payload.
+    Solr {
+        /// The URL of the failed request.
+        url: String,
+        /// The HTTP status code of the response.
+        status: u16,
+        /// The `error.msg` reported by Solr.
+        msg: String,
+        /// The `error.code` reported by Solr.
+        code: Option<i64>,
+        /// The `error.metadata` reported by Solr (e.g. `error-class`, `root-error-class`).
+        metadata: HashMap<String, String>,
+    },
+    /// Solr rejected an update because the `_version_` constraint of a document was not met
+    /// (HTTP status 409).
+    VersionConflict {
+        /// The URL of the failed request.
+        url: String,
+        /// The message reported by Solr.
+        msg: String,
+    },
+    /// A response could not be decoded or a value could not be encoded as JSON.
+    Json {
+        /// The URL of the request, if the error relates to a response.
+        url: Option<String>,
+        /// The underlying JSON error.
+        source: serde_json::Error,
+    },
+    /// Solr responded successfully, but the response does not have the expected shape.
+    UnexpectedResponse {
+        /// The URL of the request.
+        url: String,
+        /// The description of what was expected.
+        message: String,
+    },
+    /// A requested collection does not exist.
+    CollectionNotFound(String),
+    /// A builder was given an invalid input.
+    InvalidInput(String),
+}
+
+impl SolrError {
+    /// Returns the URL of the request which caused the error, if there is any.
+    pub fn url(&self) -> Option<&str> {
+        match self {
+            SolrError::Transport { url, .. }
+            | SolrError::Http { url, .. }
+            | SolrError::Solr { url, .. }
+            | SolrError::VersionConflict { url, .. }
+            | SolrError::UnexpectedResponse { url, .. } => Some(url),
+            SolrError::Json { url, .. } => url.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Returns the HTTP status code of the response which caused the error, if there is any.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            SolrError::Http { status, .. } | SolrError::Solr { status, .. } => Some(*status),
+            SolrError::VersionConflict { .. } => Some(409),
+            SolrError::Transport { source, .. } => source.status().map(|s| s.as_u16()),
+            _ => None,
+        }
+    }
+
+    /// Creates an error from the `error` object of a Solr response.
+    fn from_payload(url: String, status: u16, error: &Value) -> SolrError {
+        let msg = error["msg"].as_str()
+            .or_else(|| error["trace"].as_str())
+            .unwrap_or("Unknown error")
+            .to_string();
+        let code = error["code"].as_i64();
+        let mut metadata = HashMap::new();
+        if let Some(pairs) = error["metadata"].as_array() {
+            for pair in pairs.chunks(2) {
+                if let [key, value] = pair {
+                    let key = key.as_str().unwrap_or_default().to_string();
+                    let value = value.as_str().map(String::from).unwrap_or_else(|| value.to_string());
+                    metadata.insert(key, value);
+                }
+            }
+        }
+        SolrError::Solr { url, status, msg, code, metadata }
+    }
+}
 
-impl std::error::Error for SolrError {}
+impl std::error::Error for SolrError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SolrError::Transport { source, .. } => Some(source),
+            SolrError::Json { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
 
 impl fmt::Display for SolrError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "An error occurred!")
+        match self {
+            SolrError::Transport { url, source } => write!(f, "Request to {} failed: {}", url, source),
+            SolrError::Http { url, status, body } => {
+                write!(f, "Request to {} failed with HTTP status {}", url, status)?;
+                if !body.is_empty() {
+                    write!(f, ": {}", body)?;
+                }
+                Ok(())
+            },
+            SolrError::Solr { url, status, msg, code, metadata } => {
+                write!(f, "Solr error (HTTP status {}", status)?;
+                if let Some(code) = code {
+                    write!(f, ", code {}", code)?;
+                }
+                write!(f, ") at {}: {}", url, msg)?;
+                if let Some(class) = metadata.get("root-error-class").or_else(|| metadata.get("error-class")) {
+                    write!(f, " ({})", class)?;
+                }
+                Ok(())
+            },
+            SolrError::VersionConflict { url, msg } => write!(f, "Version conflict at {}: {}", url, msg),
+            SolrError::Json { url: Some(url), source } => write!(f, "Invalid JSON from {}: {}", url, source),
+            SolrError::Json { url: None, source } => write!(f, "JSON error: {}", source),
+            SolrError::UnexpectedResponse { url, message } => write!(f, "Unexpected response from {}: {}", url, message),
+            SolrError::CollectionNotFound(name) => write!(f, "Collection `{}` does not exist", name),
+            SolrError::InvalidInput(message) => write!(f, "Invalid input: {}", message),
+        }
     }
 }
 
 impl From<serde_json::Error> for SolrError {
-    fn from(_error: serde_json::Error) -> Self {
-        SolrError
+    fn from(error: serde_json::Error) -> Self {
+        SolrError::Json { url: None, source: error }
     }
 }
 
 impl From<reqwest::Error> for SolrError {
-    fn from(_error: reqwest::Error) -> Self {
-        SolrError
+    fn from(error: reqwest::Error) -> Self {
+        let url = error.url().map(|u| u.to_string()).unwrap_or_default();
+        SolrError::Transport { url, source: error }
     }
 }
 
@@ -101,6 +306,10 @@ pub struct Solr {
     pub host: String,
     /// A port on which is the Solr API available (e.g. `8983`).
     pub port: u16,
+    /// A pooled HTTP client shared by all requests of this client.
+    http: reqwest::Client,
+    /// Authentication sent with every request, unless overridden by `RequestOptions`.
+    auth: Option<Auth>,
 }
 
 impl Solr {
@@ -112,11 +321,38 @@ impl Solr {
     /// * `port` -
     ///
     /// # Example
+    /// ```no_run
+    /// let client = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
     /// ```
-    /// let client = solrdrv::Solr.client("http".into(), "localhost".into(), 8983);
-    /// ```
+    ///
+    /// # See
+    /// `Solr::builder` to configure the underlying HTTP client.
     pub fn client(protocol: String, host: String, port: u16) -> Solr {
-        Solr { protocol, host, port }
+        Solr { protocol, host, port, http: reqwest::Client::new(), auth: None }
+    }
+
+    /// Returns a `SolrBuilder` structure using which you can configure the HTTP client (connection
+    /// pooling, timeouts, user agent) before creating a new client.
+    ///
+    /// # Arguments
+    /// * `protocol` -
+    /// * `host` -
+    /// * `port` -
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # fn run() -> Result<(), solrdrv::SolrError> {
+    /// let client = solrdrv::Solr::builder("http".into(), "localhost".into(), 8983)
+    ///     .pool_max_idle_per_host(32)
+    ///     .connect_timeout(Duration::from_secs(5))
+    ///     .timeout(Duration::from_secs(60))
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn builder(protocol: String, host: String, port: u16) -> SolrBuilder {
+        SolrBuilder::new(protocol, host, port)
     }
 
     /// Percentage-encodes unsafe characters of a URL parameter value.
@@ -125,8 +361,9 @@ impl Solr {
     /// * `string` - The string to encode.
     ///
     /// # Example
-    /// ```
-    /// client.url_encode(&"date: [2020-05-26 TO *]".into());
+    /// ```no_run
+    /// # let client = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+    /// client.url_encode("date: [2020-05-26 TO *]");
     /// // => date%3A%20%5B2020-05-26%20TO%20%2A%5D
     /// ```
     ///
@@ -157,18 +394,42 @@ impl Solr {
     }
 
     async fn parse_fetch_result(&self, res: reqwest::Response) -> Result<serde_json::Value, SolrError> {
-        let status_code = res.status();
-        if !status_code.is_success() {
-            return Err(SolrError);
+        let url = res.url().to_string();
+        let status = res.status();
+        match res.text().await {
+            Ok(text) => Solr::parse_response_body(url, status, text),
+            Err(source) => Err(SolrError::Transport { url, source }),
+        }
+    }
+
+    /// Classifies a response by its status and body: HTTP status 409 is a version conflict, an
+    /// `error` object is a Solr error, other unsuccessful statuses are HTTP errors.
+    ///
+    /// # Arguments
+    /// * `url` - The URL of the request.
+    /// * `status` - The HTTP status of the response.
+    /// * `text` - The response body.
+    fn parse_response_body(url: String, status: reqwest::StatusCode, text: String) -> Result<serde_json::Value, SolrError> {
+        if status == reqwest::StatusCode::CONFLICT {
+            let msg = serde_json::from_str::<Value>(&text).ok()
+                .and_then(|json| json["error"]["msg"].as_str().map(String::from))
+                .unwrap_or(text);
+            return Err(SolrError::VersionConflict { url, msg });
         }
-        let text: String = res.text().await?;
         let json: Value = match serde_json::from_str(&text) {
             Ok(r) => r,
-            Err(_) => return Err(SolrError),
+            Err(source) => {
+                if status.is_success() {
+                    return Err(SolrError::Json { url: Some(url), source });
+                }
+                return Err(SolrError::Http { url, status: status.as_u16(), body: text });
+            },
         };
-        let err = json.get("error");
-        if err.is_some() {
-            return Err(SolrError);
+        if let Some(err) = json.get("error") {
+            return Err(SolrError::from_payload(url, status.as_u16(), err));
+        }
+        if !status.is_success() {
+            return Err(SolrError::Http { url, status: status.as_u16(), body: text });
         }
         Ok(json)
     }
@@ -179,22 +440,31 @@ impl Solr {
     /// * `path` -
     ///
     /// # Example
-    /// ```
-    /// let res = match client.get(&"admin/collections?action=LIST".into()).await {
-    ///     Ok(r) => r,
-    ///     Err(e) => return e,
-    /// };
-    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let client = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+    /// let res = client.get("admin/collections?action=LIST").await?;
+    /// # Ok(())
+    /// # }
     /// ```
     ///
     /// # Return
     /// If the fetch fails or the result contains an "error" key, then returns a `SolrError`,
     /// otherwise returns the fetched result.
-    pub async fn get(&self, path: &String) -> Result<serde_json::Value, SolrError> {
-        let url = self.format_url(&path);
-        println!("GET: {}", url);
-        let res = reqwest::get(&url).await?;
-        self.parse_fetch_result(res).await
+    pub async fn get(&self, path: &str) -> Result<serde_json::Value, SolrError> {
+        self.get_with(path, &RequestOptions::default()).await
+    }
+
+    /// Same as `get`, but overrides the client's authentication and headers with the specified
+    /// request options.
+    ///
+    /// # Arguments
+    /// * `path` -
+    /// * `options` -
+    pub async fn get_with(&self, path: &str, options: &RequestOptions) -> Result<serde_json::Value, SolrError> {
+        let url = self.format_url(path);
+        let req = self.apply_options(self.http.get(&url), options);
+        self.send("GET", url, req).await
     }
 
     /// Fetches a result of a POST request for the specified path.
@@ -204,39 +474,351 @@ impl Solr {
     /// * `data` -
     ///
     /// # Example
-    /// ```
+    /// ```no_run
+    /// # use solrdrv::serde_json::json;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let client = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
     /// let data = json!({ "add-field": {
     ///     "name": "birthday",
     ///     "type": "pdate",
     ///     "stored": true } });
-    /// let res = match client.post(&"users/schema".into(), &data).await {
-    ///     Ok(r) => r,
-    ///     Err(e) => return e,
-    /// };
+    /// let res = client.post("users/schema", &data).await?;
+    /// # Ok(())
+    /// # }
     /// ```
     ///
     /// # Return
     /// If the fetch fails or the result contains an "error" key, then returns a `SolrError`,
     /// otherwise returns the fetched result.
     pub async fn post(&self, path: &str, data: &serde_json::Value) -> Result<serde_json::Value, SolrError> {
+        self.post_with(path, data, &RequestOptions::default()).await
+    }
+
+    /// Same as `post`, but overrides the client's authentication and headers with the specified
+    /// request options.
+    ///
+    /// # Arguments
+    /// * `path` -
+    /// * `data` -
+    /// * `options` -
+    pub async fn post_with(&self, path: &str, data: &serde_json::Value, options: &RequestOptions)
+        -> Result<serde_json::Value, SolrError> {
         let url = self.format_url(path);
-        println!("POST: {}", url);
-        let client = reqwest::Client::new();
-        let res = client.post(&url).json(&data).send().await?;
-        self.parse_fetch_result(res).await
+        let req = self.apply_options(self.http.post(&url).json(&data), options);
+        self.send("POST", url, req).await
+    }
+
+    /// Sends a request and parses its response. With the `tracing` feature enabled, the request is
+    /// wrapped in a `solr.request` span and its outcome is emitted as an event.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    async fn send(&self, method: &'static str, url: String, req: reqwest::RequestBuilder)
+        -> Result<serde_json::Value, SolrError> {
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!("solr.request", method, url = %url);
+        let fut = async move {
+            #[cfg(feature = "tracing")]
+            let started = std::time::Instant::now();
+            let res = match req.send().await {
+                Ok(r) => r,
+                Err(source) => return Err(SolrError::Transport { url, source }),
+            };
+            #[cfg(feature = "tracing")]
+            let status = res.status().as_u16();
+            let result = self.parse_fetch_result(res).await;
+            #[cfg(feature = "tracing")]
+            {
+                let latency_ms = started.elapsed().as_millis() as u64;
+                match &result {
+                    Ok(_) => tracing::debug!(status, latency_ms, "Solr request succeeded"),
+                    Err(e) => tracing::warn!(status, latency_ms, error = %e, "Solr request failed"),
+                }
+            }
+            result
+        };
+        #[cfg(feature = "tracing")]
+        let fut = tracing::Instrument::instrument(fut, span);
+        fut.await
     }
 
-    pub async fn get_system_info(&self) -> Result<serde_json::Value, SolrError> {
-        let path = "admin/info/system?wt=json".to_string();
-        match self.get(&path).await {
-            Ok(r) => Ok(r),
-            Err(_) => Err(SolrError),
+    /// Fetches a result of a POST request with an already serialized JSON body.
+    ///
+    /// # Arguments
+    /// * `path` -
+    /// * `body` -
+    /// * `options` -
+    async fn post_raw(&self, path: &str, body: String, options: &RequestOptions) -> Result<serde_json::Value, SolrError> {
+        let url = self.format_url(path);
+        let req = self.http.post(&url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body);
+        let req = self.apply_options(req, options);
+        self.send("POST", url, req).await
+    }
+
+    /// Fetches a result of a POST request with a plain text body, which request handlers read as a
+    /// content stream.
+    ///
+    /// # Arguments
+    /// * `path` -
+    /// * `body` -
+    /// * `options` -
+    async fn post_text(&self, path: &str, body: String, options: &RequestOptions) -> Result<serde_json::Value, SolrError> {
+        let url = self.format_url(path);
+        let req = self.http.post(&url)
+            .header(reqwest::header::CONTENT_TYPE, "text/plain; charset=utf-8")
+            .body(body);
+        let req = self.apply_options(req, options);
+        self.send("POST", url, req).await
+    }
+
+    /// Adds authentication and headers to a request. Authentication from `options` takes
+    /// precedence over the client's one, headers from `options` are sent in addition to (or instead
+    /// of) the client's default headers.
+    fn apply_options(&self, mut req: reqwest::RequestBuilder, options: &RequestOptions) -> reqwest::RequestBuilder {
+        match options.auth.as_ref().or(self.auth.as_ref()) {
+            Some(Auth::Basic { username, password }) => req = req.basic_auth(username, password.as_ref()),
+            Some(Auth::Bearer(token)) => req = req.bearer_auth(token),
+            None => {},
         }
+        for (name, value) in options.headers.iter() {
+            req = req.header(name.as_str(), value.as_str());
+        }
+        req
+    }
+
+    /// Retrieves information about the Solr node (version, JVM, system).
+    pub async fn get_system_info(&self) -> Result<serde_json::Value, SolrError> {
+        self.get("admin/info/system?wt=json").await
     }
 
     /// Returns a `CollectionAPI` struct, which can be used to create and manage collections.
-    pub fn collections(&self) -> CollectionsAPI {
-        CollectionsAPI::new(&self)
+    pub fn collections(&self) -> CollectionsAPI<'_> {
+        CollectionsAPI::new(self)
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Credentials used to authenticate requests
+pub enum Auth {
+    /// HTTP Basic authentication, as used by the Solr `BasicAuthPlugin`.
+    Basic {
+        /// The user name.
+        username: String,
+        /// The password.
+        password: Option<String>,
+    },
+    /// A bearer token (e.g. a JWT, as used by the Solr `JWTAuthPlugin`).
+    Bearer(String),
+}
+
+#[derive(Debug, Clone, Default)]
+/// Options of a single request, which override the defaults of a client
+pub struct RequestOptions {
+    /// If set, then it is used instead of the client's authentication.
+    pub auth: Option<Auth>,
+    /// Headers sent in addition to the client's default headers.
+    pub headers: HashMap<String, String>,
+}
+
+impl RequestOptions {
+    /// Sets the authentication used for the request.
+    ///
+    /// # Arguments
+    /// * `auth` -
+    pub fn auth(&mut self, auth: Auth) -> &mut Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Sets a header sent with the request.
+    ///
+    /// # Arguments
+    /// * `name` - The header name.
+    /// * `value` - The header value.
+    pub fn header(&mut self, name: String, value: String) -> &mut Self {
+        self.headers.insert(name, value);
+        self
+    }
+}
+
+#[derive(Debug)]
+/// A builder for Solr clients
+pub struct SolrBuilder {
+    protocol: String,
+    host: String,
+    port: u16,
+    pool_idle_timeout: Option<Duration>,
+    pool_max_idle_per_host: Option<usize>,
+    tcp_keepalive: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    user_agent: Option<String>,
+    auth: Option<Auth>,
+    headers: HashMap<String, String>,
+}
+
+impl SolrBuilder {
+    /// Creates a new client builder.
+    ///
+    /// # Arguments
+    /// * `protocol` - A protocol on which is the Solr API available (e.g. `http`, `https`).
+    /// * `host` - A host name on which is the Solr API available (e.g. `localhost`).
+    /// * `port` - A port on which is the Solr API available (e.g. `8983`).
+    pub fn new(protocol: String, host: String, port: u16) -> SolrBuilder {
+        SolrBuilder {
+            protocol,
+            host,
+            port,
+            pool_idle_timeout: None,
+            pool_max_idle_per_host: None,
+            tcp_keepalive: None,
+            connect_timeout: None,
+            timeout: None,
+            user_agent: None,
+            auth: None,
+            headers: HashMap::new(),
+        }
+    }
+
+    /// Sets how long an idle connection is kept alive in the pool. Defaults to 90 seconds.
+    ///
+    /// # Arguments
+    /// * `pool_idle_timeout` -
+    pub fn pool_idle_timeout(&mut self, pool_idle_timeout: Duration) -> &mut Self {
+        self.pool_idle_timeout = Some(pool_idle_timeout);
+        self
+    }
+
+    /// Sets the maximum number of idle connections kept in the pool. Defaults to no limit.
+    ///
+    /// # Arguments
+    /// * `pool_max_idle_per_host` -
+    pub fn pool_max_idle_per_host(&mut self, pool_max_idle_per_host: usize) -> &mut Self {
+        self.pool_max_idle_per_host = Some(pool_max_idle_per_host);
+        self
+    }
+
+    /// Enables TCP keep-alive probes with the specified interval on pooled connections.
+    ///
+    /// # Arguments
+    /// * `tcp_keepalive` -
+    pub fn tcp_keepalive(&mut self, tcp_keepalive: Duration) -> &mut Self {
+        self.tcp_keepalive = Some(tcp_keepalive);
+        self
+    }
+
+    /// Sets a timeout for establishing a connection. Defaults to no timeout.
+    ///
+    /// # Arguments
+    /// * `connect_timeout` -
+    pub fn connect_timeout(&mut self, connect_timeout: Duration) -> &mut Self {
+        self.connect_timeout = Some(connect_timeout);
+        self
+    }
+
+    /// Sets a timeout for a whole request, from sending it until its response is completely read.
+    /// Defaults to no timeout.
+    ///
+    /// # Arguments
+    /// * `timeout` -
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the `User-Agent` header sent with every request.
+    ///
+    /// # Arguments
+    /// * `user_agent` -
+    pub fn user_agent(&mut self, user_agent: String) -> &mut Self {
+        self.user_agent = Some(user_agent);
+        self
+    }
+
+    /// Authenticates every request using HTTP Basic authentication.
+    ///
+    /// # Arguments
+    /// * `username` -
+    /// * `password` -
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/basic-authentication-plugin.html
+    pub fn basic_auth(&mut self, username: String, password: Option<String>) -> &mut Self {
+        self.auth = Some(Auth::Basic { username, password });
+        self
+    }
+
+    /// Authenticates every request using a bearer token (e.g. a JWT).
+    ///
+    /// # Arguments
+    /// * `token` -
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/jwt-authentication-plugin.html
+    pub fn bearer_auth(&mut self, token: String) -> &mut Self {
+        self.auth = Some(Auth::Bearer(token));
+        self
+    }
+
+    /// Sets a header sent with every request.
+    ///
+    /// # Arguments
+    /// * `name` - The header name.
+    /// * `value` - The header value.
+    pub fn header(&mut self, name: String, value: String) -> &mut Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// Builds a new client with specified properties.
+    ///
+    /// # Return
+    /// If the HTTP client cannot be initialized (e.g. because of an invalid header or a TLS
+    /// backend failure), then returns `SolrError::InvalidInput`.
+    pub fn build(&self) -> Result<Solr, SolrError> {
+        let mut builder = reqwest::Client::builder();
+        if let Some(pool_idle_timeout) = self.pool_idle_timeout {
+            builder = builder.pool_idle_timeout(pool_idle_timeout);
+        }
+        if let Some(pool_max_idle_per_host) = self.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(pool_max_idle_per_host);
+        }
+        if let Some(tcp_keepalive) = self.tcp_keepalive {
+            builder = builder.tcp_keepalive(tcp_keepalive);
+        }
+        if let Some(connect_timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(connect_timeout);
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(user_agent) = &self.user_agent {
+            builder = builder.user_agent(user_agent.as_str());
+        }
+        let mut headers = HeaderMap::new();
+        for (name, value) in self.headers.iter() {
+            let name = match HeaderName::from_bytes(name.as_bytes()) {
+                Ok(n) => n,
+                Err(_) => return Err(SolrError::InvalidInput(format!("invalid header name `{}`", name))),
+            };
+            let value = match HeaderValue::from_str(value) {
+                Ok(v) => v,
+                Err(_) => return Err(SolrError::InvalidInput(format!("invalid value of header `{}`", name))),
+            };
+            headers.insert(name, value);
+        }
+        builder = builder.default_headers(headers);
+        let http = match builder.build() {
+            Ok(h) => h,
+            Err(e) => return Err(SolrError::InvalidInput(format!("cannot build HTTP client: {}", e))),
+        };
+        Ok(Solr {
+            protocol: self.protocol.clone(),
+            host: self.host.clone(),
+            port: self.port,
+            http,
+            auth: self.auth.clone(),
+        })
     }
 }
 
@@ -249,7 +831,7 @@ pub struct CollectionsAPI<'a> {
 impl<'a> CollectionsAPI<'a> {
     fn new(client: &'a Solr) -> CollectionsAPI<'a> {
         CollectionsAPI {
-            client: &client
+            client
         }
     }
 
@@ -259,47 +841,52 @@ impl<'a> CollectionsAPI<'a> {
     /// # Arguments
     /// * `name` - The name of the collection.
     pub fn create(&self, name: String) -> CollectionBuilder<'a> {
-        CollectionBuilder::new(&self.client, name)
+        CollectionBuilder::new(self.client, name)
     }
 
     /// Returns a list of existing collections.
-    pub async fn list(&self) -> Result<Vec<Collection<'_>>, SolrError> {
-        let path = "admin/collections?action=LIST".to_string();
-        let res = match self.client.get(&path).await {
-            Ok(r) => r,
-            Err(_) => return Err(SolrError),
-        };
+    async fn list_names(&self) -> Result<Vec<String>, SolrError> {
+        let path = "admin/collections?action=LIST";
+        let res = self.client.get(path).await?;
 
-        let obj = match res["collections"].as_array().cloned() {
+        let unexpected = || SolrError::UnexpectedResponse {
+            url: self.client.format_url(path),
+            message: "expected `collections` to be an array of names".into(),
+        };
+        let obj = match res["collections"].as_array() {
             Some(o) => o,
-            None => return Err(SolrError),
+            None => return Err(unexpected()),
         };
 
-        let mut collections: Vec<Collection> = vec![];
-        for c in obj.into_iter() {
-            let name = String::from(c.as_str().unwrap());
-            let col = Collection::new(&self.client, name);
-            collections.push(col);
+        let mut names = vec![];
+        for c in obj.iter() {
+            match c.as_str() {
+                Some(name) => names.push(String::from(name)),
+                None => return Err(unexpected()),
+            }
         }
-        Ok(collections)
+        Ok(names)
+    }
+
+    /// Returns a list of existing collections.
+    pub async fn list(&self) -> Result<Vec<Collection<'a>>, SolrError> {
+        let names = self.list_names().await?;
+        Ok(names.into_iter().map(|name| Collection::new(self.client, name)).collect())
     }
 
     /// Returns an already existing collection with specified name.
     ///
     /// # Arguments
     /// * `name` - The name of the collection to retrieve.
-    pub async fn get(&self, name: String) -> Result<Collection<'_>, SolrError> {
-        let path = "admin/collections?action=LIST".to_string();
-        let res = match self.client.get(&path).await {
-            Ok(r) => r,
-            Err(_) => return Err(SolrError),
-        };
-        for c in res["collections"].as_array().unwrap() {
-            if c.as_str().unwrap().cmp(name.as_str()) == std::cmp::Ordering::Equal {
-                return Ok(Collection::new(&self.client, name.clone()));
-            }
+    ///
+    /// # Return
+    /// If the collection does not exist, then returns `SolrError::CollectionNotFound`.
+    pub async fn get(&self, name: String) -> Result<Collection<'a>, SolrError> {
+        let names = self.list_names().await?;
+        if names.contains(&name) {
+            return Ok(Collection::new(self.client, name));
         }
-        Err(SolrError)
+        Err(SolrError::CollectionNotFound(name))
     }
 
     /// Deletes an existing collection with specified name.
@@ -307,11 +894,9 @@ impl<'a> CollectionsAPI<'a> {
     /// # Arguments
     /// * `name` - The name of the collection to delete.
     pub async fn delete(&self, name: &str) -> Result<(), SolrError> {
-        let path = format!("admin/collections?action=DELETE&name={}", name).to_string();
-        match self.client.get(&path).await {
-            Ok(_) => Ok(()),
-            Err(_) => Err(SolrError)
-        }
+        let path = format!("admin/collections?action=DELETE&name={}", self.client.url_encode(name));
+        self.client.get(&path).await?;
+        Ok(())
     }
 }
 
@@ -321,8 +906,12 @@ pub struct Collection<'a> {
     client: &'a Solr,
     /// The name of the collection.
     pub name: String,
-    /// Docs enqueued for commit.
-    docs_to_commit: Vec<serde_json::Value>,
+    /// Update commands (adds, deletes) enqueued for commit.
+    commands_to_commit: Vec<UpdateCommand>,
+    /// Options used by `commit`.
+    commit_options: CommitOptions,
+    /// Request options of update requests.
+    options: RequestOptions,
     /// Set if an error occurs during docs commit.
     error: Option<SolrError>,
 }
@@ -330,21 +919,101 @@ pub struct Collection<'a> {
 impl<'a> Collection<'a> {
     fn new(client: &'a Solr, name: String) -> Collection<'a> {
         Collection {
-            client: &client,
-            name: name,
-            docs_to_commit: vec![],
+            client,
+            name,
+            commands_to_commit: vec![],
+            commit_options: CommitOptions::default(),
+            options: RequestOptions::default(),
             error: None,
         }
     }
 
+    /// Overrides the client's authentication for update requests of the collection (`commit`,
+    /// `commit_with`, `hard_commit`, `soft_commit` and `optimize`) and for bulk indexers created
+    /// afterwards. Other requests (e.g. `search`) set their own authentication.
+    ///
+    /// # Arguments
+    /// * `auth` -
+    pub fn auth(&mut self, auth: Auth) -> &mut Self {
+        self.options.auth(auth);
+        self
+    }
+
+    /// Sets a header sent with update requests of the collection (see `auth`).
+    ///
+    /// # Arguments
+    /// * `name` - The header name.
+    /// * `value` - The header value.
+    pub fn header(&mut self, name: String, value: String) -> &mut Self {
+        self.options.header(name, value);
+        self
+    }
+
     /// Returns a `SchemaAPI` struct which is used to modify schema of a collection.
     pub fn schema(&self) -> SchemaAPI<'a, '_> {
-        SchemaAPI::new(&self)
+        SchemaAPI::new(self)
     }
 
     /// Returns a `Query` struct which is used to search for documents within a collection.
     pub fn search(&self) -> Query<'a, '_> {
-        Query::new(&self)
+        Query::new(self)
+    }
+
+    /// Returns a `MoreLikeThis` struct which is used to find documents similar to a document or
+    /// a text.
+    pub fn more_like_this(&self) -> MoreLikeThis<'_, 'a> {
+        MoreLikeThis::new(self)
+    }
+
+    /// Returns a `Suggester` struct which is used to get autocomplete suggestions from the
+    /// `/suggest` handler.
+    pub fn suggest(&self) -> Suggester<'_, 'a> {
+        Suggester::new(self)
+    }
+
+    /// Returns a `Terms` struct which is used to enumerate indexed terms of fields via the
+    /// `/terms` handler.
+    pub fn terms(&self) -> Terms<'_, 'a> {
+        Terms::new(self)
+    }
+
+    /// Returns a `RealTimeGet` struct which is used to get the latest version of a document by its
+    /// unique key, even if it was not committed yet.
+    ///
+    /// # Arguments
+    /// * `id` - The unique key of the document.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # #[derive(serde::Deserialize)]
+    /// # struct User { name: String }
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+    /// # let mut users = solr.collections().get("users".into()).await?;
+    /// let user: Option<User> = users.get_by_id("1")
+    ///     .fq("age:[18 TO *]")
+    ///     .first_as().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_by_id(&self, id: &str) -> RealTimeGet<'_, 'a> {
+        RealTimeGet::new(self, vec![id.to_string()])
+    }
+
+    /// Returns a `RealTimeGet` struct which is used to get the latest versions of documents by
+    /// their unique keys, even if they were not committed yet.
+    ///
+    /// # Arguments
+    /// * `ids` - The unique keys of the documents.
+    pub fn get_by_ids<S>(&self, ids: &[S]) -> RealTimeGet<'_, 'a>
+        where S: AsRef<str> {
+        RealTimeGet::new(self, ids.iter().map(|id| id.as_ref().to_string()).collect())
+    }
+
+    /// Returns a `BulkIndexer` struct which is used to index large amounts of documents from a
+    /// stream or a channel in concurrent batches.
+    pub fn bulk_indexer(&self) -> BulkIndexer<'_, 'a> {
+        BulkIndexer::new(self)
     }
 
     /// Enqueues a document to be added into a collection. Use `commit` to actually send the enqueued
@@ -352,10 +1021,18 @@ impl<'a> Collection<'a> {
     ///
     /// # Arguments
     /// * `document` - Can be either an object for single document or an array of objects for
-    /// multiple documents.
+    ///   multiple documents. A document can contain nested child documents, either in the
+    ///   `_childDocuments_` field or in labelled fields (see `nested`).
+    ///
+    /// If the document is not an object nor an array of objects, or if it contains a child
+    /// document which is not an object, then the next `commit` returns `SolrError::InvalidInput`.
     ///
     /// # Example
-    /// ```
+    /// ```no_run
+    /// # use solrdrv::serde_json::json;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+    /// # let mut users = solr.collections().get("users".into()).await?;
     /// users.add(json!({ "name": "Some", "age": 19 }))
     ///     .add(json!({ "name": "Dude", "age": 21 }));
     ///
@@ -365,66 +1042,376 @@ impl<'a> Collection<'a> {
     ///     { "name": "Some", "age": 19 },
     ///     { "name": "Dude", "age": 21 }
     /// ]));
+    /// # Ok(())
+    /// # }
     /// ```
     pub fn add(&mut self, document: serde_json::Value) -> &mut Self {
-        if document.is_array() {
-            for doc in document.as_array().unwrap().clone() {
-                if !doc.is_object() {
-                    self.error = Some(SolrError);
-                    break;
+        match document {
+            Value::Array(docs) => {
+                for doc in docs {
+                    if let Err(e) = nested::validate_document(&doc) {
+                        self.error = Some(e);
+                        break;
+                    }
+                    self.commands_to_commit.push(UpdateCommand::Add(doc));
                 }
-                self.docs_to_commit.push(doc);
-            }
-        } else if document.is_object() {
-            self.docs_to_commit.push(document);
+            },
+            Value::Object(_) => match nested::validate_document(&document) {
+                Ok(_) => self.commands_to_commit.push(UpdateCommand::Add(document)),
+                Err(e) => self.error = Some(e),
+            },
+            _ => {
+                self.error = Some(SolrError::InvalidInput(
+                    format!("expected a document object or an array of objects, got `{}`", document)));
+            },
         }
         self
     }
 
-    /// Return a number of documents enqueued for adding into a collection.
-    pub fn get_commit_size(&self) -> usize {
-        self.docs_to_commit.len()
+    /// Enqueues a document to be added into a collection only if its `_version_` constraint is
+    /// met. Use `commit` to actually send the enqueued documents.
+    ///
+    /// # Arguments
+    /// * `document` - The document object.
+    /// * `version` - A positive version must match the version of the existing document exactly,
+    ///   `1` requires the document to exist, a negative version requires the document to not exist.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use solrdrv::serde_json::json;
+    /// # use solrdrv::SolrError;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+    /// # let mut users = solr.collections().get("users".into()).await?;
+    /// # let version = 1;
+    /// match users.add_versioned(json!({ "id": "1", "name": "Some" }), version).commit().await {
+    ///     Err(SolrError::VersionConflict { .. }) => { /* reload and retry */ },
+    ///     res => res?,
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Return
+    /// If the constraint is not met, then `commit` returns `SolrError::VersionConflict`.
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/updating-parts-of-documents.html#optimistic-concurrency
+    pub fn add_versioned(&mut self, document: serde_json::Value, version: i64) -> &mut Self {
+        match document {
+            Value::Object(mut doc) => {
+                doc.insert(VERSION_FIELD.into(), json!(version));
+                let doc = Value::Object(doc);
+                match nested::validate_document(&doc) {
+                    Ok(_) => self.commands_to_commit.push(UpdateCommand::Add(doc)),
+                    Err(e) => self.error = Some(e),
+                }
+            },
+            _ => {
+                self.error = Some(SolrError::InvalidInput(
+                    format!("expected a document object, got `{}`", document)));
+            },
+        }
+        self
     }
 
-    /// Sends enqueued documents into a collection.
+    /// Enqueues typed documents to be added into a collection. Each document must serialize into a
+    /// JSON object. Use `commit` to actually send the enqueued documents.
+    ///
+    /// # Arguments
+    /// * `docs` - The documents to add.
     ///
     /// # Example
+    /// ```no_run
+    /// # use chrono::{DateTime, Utc};
+    /// # use serde::Serialize;
+    /// #[derive(Serialize)]
+    /// struct User {
+    ///     name: String,
+    ///     age: u32,
+    ///     tags: Vec<String>,
+    ///     #[serde(with = "solrdrv::serde_helpers::solr_date")]
+    ///     birthday: DateTime<Utc>,
+    /// }
+    ///
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+    /// # let mut users = solr.collections().get("users".into()).await?;
+    /// # let some = User { name: "Some".into(), age: 19, tags: vec![], birthday: Utc::now() };
+    /// # let dude = User { name: "Dude".into(), age: 21, tags: vec![], birthday: Utc::now() };
+    /// users.add_docs(&[some, dude]).commit().await?;
+    /// # Ok(())
+    /// # }
     /// ```
-    /// users.add(json!({"name": "Some" })).commit().await?;
-    /// ```
-    pub async fn commit(&mut self) -> Result<(), SolrError> {
-        if self.error.is_some() {
-            let error = std::mem::replace(&mut self.error, None).unwrap();
-            return Err(error);
+    ///
+    /// # See
+    /// `serde_helpers` for (de)serializing Solr dates and multi-valued fields.
+    pub fn add_docs<T>(&mut self, docs: &[T]) -> &mut Self
+        where T: serde::ser::Serialize {
+        for doc in docs {
+            match serde_json::to_value(doc) {
+                Ok(doc) => match nested::validate_document(&doc) {
+                    Ok(_) => self.commands_to_commit.push(UpdateCommand::Add(doc)),
+                    Err(e) => {
+                        self.error = Some(e);
+                        break;
+                    },
+                },
+                Err(e) => {
+                    self.error = Some(e.into());
+                    break;
+                },
+            }
         }
+        self
+    }
 
-        if self.docs_to_commit.is_empty() {
-            println!("Info: No documents to commit, skipping...");
-            return Ok(());
+    /// Enqueues an atomic update of a single document. Use `commit` to actually send the enqueued
+    /// commands.
+    ///
+    /// # Arguments
+    /// * `update` - The update to enqueue.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use solrdrv::AtomicUpdate;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+    /// # let mut users = solr.collections().get("users".into()).await?;
+    /// users.update(AtomicUpdate::new("1")
+    ///         .set("name", "Some")
+    ///         .inc("age", 1))
+    ///     .update(AtomicUpdate::new("2").remove("tags", "old"))
+    ///     .commit().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/updating-parts-of-documents.html#atomic-updates
+    pub fn update(&mut self, update: &AtomicUpdate) -> &mut Self {
+        match update.build() {
+            Ok(doc) => self.commands_to_commit.push(UpdateCommand::Add(doc)),
+            Err(e) => self.error = Some(e),
         }
-
-        let path = format!("{}/update?commit=true", self.name);
-        let res = match self.client.post(&path, &json!(self.docs_to_commit)).await {
-            Ok(_) => Ok(()),
-            Err(_) => Err(SolrError),
-        };
-        self.docs_to_commit.clear();
-        res
+        self
     }
-}
 
-#[derive(Debug)]
-/// A builder for collections
+    /// Enqueues a command to delete a document by its unique key. Use `commit` to actually send
+    /// the enqueued commands.
+    ///
+    /// # Arguments
+    /// * `id` - The unique key of the document to delete.
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/uploading-data-with-index-handlers.html#sending-json-update-commands
+    pub fn delete_by_id(&mut self, id: &str) -> &mut Self {
+        self.commands_to_commit.push(UpdateCommand::DeleteById(id.to_string()));
+        self
+    }
+
+    /// Enqueues commands to delete documents by their unique keys. Use `commit` to actually send
+    /// the enqueued commands.
+    ///
+    /// # Arguments
+    /// * `ids` - The unique keys of the documents to delete.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use solrdrv::serde_json::json;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+    /// # let mut users = solr.collections().get("users".into()).await?;
+    /// users.delete_by_ids(&["1", "2"])
+    ///     .add(json!({ "id": "3", "name": "Some" }))
+    ///     .commit().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn delete_by_ids<S>(&mut self, ids: &[S]) -> &mut Self
+        where S: AsRef<str> {
+        for id in ids {
+            self.delete_by_id(id.as_ref());
+        }
+        self
+    }
+
+    /// Enqueues a command to delete all documents matching a query. Use `commit` to actually send
+    /// the enqueued commands.
+    ///
+    /// # Arguments
+    /// * `query` - The query matching documents to delete (e.g. `age:[* TO 18}`).
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/uploading-data-with-index-handlers.html#sending-json-update-commands
+    pub fn delete_by_query(&mut self, query: &str) -> &mut Self {
+        self.commands_to_commit.push(UpdateCommand::DeleteByQuery(query.to_string()));
+        self
+    }
+
+    /// Return a number of commands (added documents, deletes) enqueued for commit.
+    pub fn get_commit_size(&self) -> usize {
+        self.commands_to_commit.len()
+    }
+
+    /// Sets options of the commit performed by `commit`. Defaults to a hard commit.
+    ///
+    /// # Arguments
+    /// * `options` -
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use solrdrv::CommitOptions;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+    /// # let mut users = solr.collections().get("users".into()).await?;
+    /// users.commit_options(&CommitOptions::within(10_000));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn commit_options(&mut self, options: &CommitOptions) -> &mut Self {
+        self.commit_options = options.clone();
+        self
+    }
+
+    /// Sends enqueued commands into a collection. All commands are sent in a single request in the
+    /// order in which they were enqueued. The commands are committed as set by `commit_options`
+    /// (a hard commit by default).
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use solrdrv::serde_json::json;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+    /// # let mut users = solr.collections().get("users".into()).await?;
+    /// users.add(json!({"name": "Some" })).commit().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn commit(&mut self) -> Result<(), SolrError> {
+        let options = self.commit_options.clone();
+        self.commit_with(&options).await
+    }
+
+    /// Same as `commit`, but commits the commands as set by the specified options.
+    ///
+    /// # Arguments
+    /// * `options` -
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use solrdrv::serde_json::json;
+    /// # use solrdrv::CommitOptions;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+    /// # let mut users = solr.collections().get("users".into()).await?;
+    /// users.add(json!({"name": "Some" }))
+    ///     .commit_with(CommitOptions::soft().wait_searcher(false)).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/updatehandlers-in-solrconfig.html#commits
+    pub async fn commit_with(&mut self, options: &CommitOptions) -> Result<(), SolrError> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+
+        if self.commands_to_commit.is_empty() {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(collection = %self.name, "No commands to commit, skipping");
+            return Ok(());
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::info!(collection = %self.name, commands = self.commands_to_commit.len(), "Committing commands");
+        let path = format!("{}/update{}", self.name, options.to_query_string());
+        let body = UpdateCommand::to_body(&self.commands_to_commit);
+        self.commands_to_commit.clear();
+        self.client.post_raw(&path, body?, &self.options).await.map(|_| ())
+    }
+
+    /// Performs a hard commit of all uncommitted documents, independently of enqueued commands.
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/updatehandlers-in-solrconfig.html#commits
+    pub async fn hard_commit(&self) -> Result<(), SolrError> {
+        let path = format!("{}/update?commit=true", self.name);
+        self.client.get_with(&path, &self.options).await.map(|_| ())
+    }
+
+    /// Performs a soft commit of all uncommitted documents, independently of enqueued commands.
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/updatehandlers-in-solrconfig.html#commits
+    pub async fn soft_commit(&self) -> Result<(), SolrError> {
+        let path = format!("{}/update?softCommit=true", self.name);
+        self.client.get_with(&path, &self.options).await.map(|_| ())
+    }
+
+    /// Optimizes the index of a collection, i.e. merges its segments.
+    ///
+    /// # Arguments
+    /// * `max_segments` - The maximum number of segments to merge the index into (defaults to 1).
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/uploading-data-with-index-handlers.html#commit-and-optimize-operations
+    pub async fn optimize(&self, max_segments: Option<usize>) -> Result<(), SolrError> {
+        let mut path = format!("{}/update?optimize=true", self.name);
+        if let Some(max_segments) = max_segments {
+            path = format!("{}&maxSegments={}", path, max_segments);
+        }
+        self.client.get_with(&path, &self.options).await.map(|_| ())
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A command of the JSON update handler
+enum UpdateCommand {
+    /// Adds (or replaces) a document.
+    Add(Value),
+    /// Deletes a document by its unique key.
+    DeleteById(String),
+    /// Deletes all documents matching a query.
+    DeleteByQuery(String),
+}
+
+impl UpdateCommand {
+    /// Returns the command as a `"name": {...}` member of a JSON update body.
+    fn to_member(&self) -> Result<String, SolrError> {
+        let (name, value) = match self {
+            UpdateCommand::Add(doc) => ("add", json!({ "doc": doc })),
+            UpdateCommand::DeleteById(id) => ("delete", json!({ "id": id })),
+            UpdateCommand::DeleteByQuery(query) => ("delete", json!({ "query": query })),
+        };
+        Ok(format!("\"{}\":{}", name, serde_json::to_string(&value)?))
+    }
+
+    /// Returns a JSON update body executing the commands in order. Since a command name can repeat,
+    /// the body cannot be represented by a `serde_json::Value` object.
+    fn to_body(commands: &[UpdateCommand]) -> Result<String, SolrError> {
+        let members = commands.iter()
+            .map(UpdateCommand::to_member)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(format!("{{{}}}", members.join(",")))
+    }
+}
+
+#[derive(Debug)]
+/// A builder for collections
 pub struct CollectionBuilder<'a> {
     client: &'a Solr,
     params: HashMap<String, String>,
+    options: RequestOptions,
 }
 
 impl<'a> CollectionBuilder<'a> {
     fn new<'b: 'a>(client: &'b Solr, name: String) -> CollectionBuilder<'a> {
         let mut collection_builder = CollectionBuilder {
-            client: &client,
+            client,
             params: HashMap::new(),
+            options: RequestOptions::default(),
         };
         collection_builder.set("name".into(), name);
         collection_builder
@@ -657,6 +1644,25 @@ impl<'a> CollectionBuilder<'a> {
         self.set("alias".into(), alias)
     }
 
+    /// Overrides the client's authentication for the create request.
+    ///
+    /// # Arguments
+    /// * `auth` -
+    pub fn auth(&mut self, auth: Auth) -> &mut Self {
+        self.options.auth(auth);
+        self
+    }
+
+    /// Sets a header sent with the create request.
+    ///
+    /// # Arguments
+    /// * `name` - The header name.
+    /// * `value` - The header value.
+    pub fn header(&mut self, name: String, value: String) -> &mut Self {
+        self.options.header(name, value);
+        self
+    }
+
     fn build_path(&self) -> String {
         let mut path = "admin/collections?action=CREATE".into();
         for (k, v) in self.params.iter() {
@@ -669,29 +1675,32 @@ impl<'a> CollectionBuilder<'a> {
     ///
     /// # Example
     /// Following example creates a new `users` collection.
-    /// ```
+    /// ```no_run
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
     /// let mut users = solr.collections()
     ///     .create("users".into())
     ///     .router_field("id".into())
     ///     .num_shards(16)
     ///     .max_shards_per_node(16)
     ///     .commit().await?;
+    /// # Ok(())
+    /// # }
     /// ```
     pub async fn commit(&mut self) -> Result<Collection<'a>, SolrError> {
-        let name = self.params.get("name".into());
-        if name.is_none() {
-            return Err(SolrError);
-        }
-        let name = name.unwrap().clone();
-        let path = self.build_path();
-        let res = match self.client.get(&path).await {
-            Ok(r) => r,
-            Err(_) => return Err(SolrError),
+        let name = match self.params.get("name") {
+            Some(n) if !n.is_empty() => n.clone(),
+            _ => return Err(SolrError::InvalidInput("collection name must not be empty".into())),
         };
+        let path = self.build_path();
+        let res = self.client.get_with(&path, &self.options).await?;
         if res.get("success").is_none() {
-            return Err(SolrError);
+            return Err(SolrError::UnexpectedResponse {
+                url: self.client.format_url(&path),
+                message: format!("collection `{}` was not created: {}", name, res),
+            });
         }
-        let col = Collection::new(&self.client, name);
+        let col = Collection::new(self.client, name);
         Ok(col)
     }
 }
@@ -722,9 +1731,9 @@ impl FieldBuilder {
     /// * `value` - The property value.
     ///
     /// # Example
-    /// ```
+    /// ```no_run
     /// let name = solrdrv::FieldBuilder::new("name".into())
-    ///     .set("type".into(), "string".into())
+    ///     .set("type".into(), "string")
     ///     .set("omitNorms".into(), true)
     ///     .set("stored".into(), true)
     ///     .build().unwrap();
@@ -1024,10 +2033,46 @@ impl FieldBuilder {
             .build().unwrap()
     }
 
+    /// Returns a prebuilt `_root_` field, required for nested documents.
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/indexing-nested-documents.html#schema-configuration
+    pub fn root() -> serde_json::Value {
+        FieldBuilder::new(ROOT_FIELD.into())
+            .typename("string".into())
+            .indexed(true)
+            .stored(false)
+            .doc_values(false)
+            .build().unwrap()
+    }
+
+    /// Returns a prebuilt `_nest_path_` field, required for labelled child documents. The schema
+    /// must contain the `_nest_path_` field type (`solr.NestPathField`).
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/indexing-nested-documents.html#schema-configuration
+    pub fn nest_path() -> serde_json::Value {
+        FieldBuilder::new(NEST_PATH_FIELD.into())
+            .typename(NEST_PATH_FIELD.into())
+            .build().unwrap()
+    }
+
+    /// Returns a prebuilt `_nest_parent_` field holding the unique key of the parent document.
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/indexing-nested-documents.html#schema-configuration
+    pub fn nest_parent() -> serde_json::Value {
+        FieldBuilder::new(NEST_PARENT_FIELD.into())
+            .typename("string".into())
+            .indexed(true)
+            .stored(true)
+            .build().unwrap()
+    }
+
     /// Builds a new field descriptor with specified properties.
     ///
     /// # Example
-    /// ```
+    /// ```no_run
     /// let name = solrdrv::FieldBuilder::new("name".into())
     ///     .typename("string".into())
     ///     .omit_norms(true)
@@ -1051,7 +2096,7 @@ pub struct SchemaAPI<'a, 'b> {
 impl<'a, 'b> SchemaAPI<'a, 'b> {
     fn new(collection: &'a Collection<'b>) -> SchemaAPI<'a, 'b> {
         SchemaAPI {
-            collection: &collection,
+            collection,
             fields_to_add: vec![],
             fields_to_delete: vec![],
             fields_to_replace: vec![],
@@ -1067,6 +2112,31 @@ impl<'a, 'b> SchemaAPI<'a, 'b> {
         self.collection.client.get(&path).await
     }
 
+    /// Retrieves the name of the collection's unique key field.
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/schema-api.html#list-uniquekey
+    pub async fn unique_key(&self) -> Result<String, SolrError> {
+        self.unique_key_with(&RequestOptions::default()).await
+    }
+
+    /// Same as `unique_key`, but overrides the client's authentication and headers with the
+    /// specified request options.
+    ///
+    /// # Arguments
+    /// * `options` -
+    pub async fn unique_key_with(&self, options: &RequestOptions) -> Result<String, SolrError> {
+        let path = format!("{}/schema/uniquekey", self.collection.name);
+        let res = self.collection.client.get_with(&path, options).await?;
+        match res["uniqueKey"].as_str() {
+            Some(key) => Ok(key.to_string()),
+            None => Err(SolrError::UnexpectedResponse {
+                url: self.collection.client.format_url(&path),
+                message: "missing `uniqueKey`".into(),
+            }),
+        }
+    }
+
     /// Enqueues a command to add a new field to a collection. Use `commit` to actually execute all
     /// enqueued commands.
     ///
@@ -1076,11 +2146,16 @@ impl<'a, 'b> SchemaAPI<'a, 'b> {
     /// # Example
     /// Following example adds fields `name` and `age` into a collection `users` and commits the
     /// changes.
-    /// ```
+    /// ```no_run
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+    /// # let mut users = solr.collections().get("users".into()).await?;
     /// users.schema()
     ///     .add_field(solrdrv::FieldBuilder::string("name".into()))
     ///     .add_field(solrdrv::FieldBuilder::numeric("age".into()))
     ///     .commit().await?;
+    /// # Ok(())
+    /// # }
     /// ```
     ///
     /// # See
@@ -1121,20 +2196,34 @@ impl<'a, 'b> SchemaAPI<'a, 'b> {
     /// # Example
     /// Following code adds a field `name` into a collection `users`, removes its field `age` and
     /// commits the changes.
-    /// ```
-    /// users.scheme()
+    /// ```no_run
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+    /// # let mut users = solr.collections().get("users".into()).await?;
+    /// users.schema()
     ///     .add_field(solrdrv::FieldBuilder::string("name".into()))
-    ///     .delete_field("age".into())
+    ///     .delete_field("age")
     ///     .commit().await?;
+    /// # Ok(())
+    /// # }
     /// ```
     pub async fn commit(&mut self) -> Result<(), SolrError> {
         if self.fields_to_add.is_empty()
             && self.fields_to_delete.is_empty()
             && self.fields_to_replace.is_empty() {
-            println!("Info: No schema changes to commit, skipping...");
+            #[cfg(feature = "tracing")]
+            tracing::debug!(collection = %self.collection.name, "No schema changes to commit, skipping");
             return Ok(());
         }
 
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            collection = %self.collection.name,
+            add = self.fields_to_add.len(),
+            delete = self.fields_to_delete.len(),
+            replace = self.fields_to_replace.len(),
+            "Committing schema changes");
+
         let path = format!("{}/schema", self.collection.name);
         let mut data = json!({});
 
@@ -1153,29 +2242,45 @@ impl<'a, 'b> SchemaAPI<'a, 'b> {
             self.fields_to_add.clear();
         }
 
-        match self.collection.client.post(&path, &data).await {
-            Ok(_) => Ok(()),
-            Err(_) => Err(SolrError),
-        }
+        self.collection.client.post(&path, &data).await?;
+        Ok(())
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A transport of a query
+pub enum QueryTransport {
+    /// Sends the parameters in the URL of a GET request to the `/select` handler. Queries with a
+    /// URL longer than `MAX_GET_URL_LENGTH` are sent as `QueryTransport::Json`.
+    Get,
+    /// Sends the query as a JSON body of a POST request to the `/query` handler (JSON Request
+    /// API).
+    Json,
+}
+
 #[derive(Debug)]
 /// A query API
 pub struct Query<'a, 'b> {
     collection: &'a Collection<'b>,
-    params: HashMap<String, String>
+    params: HashMap<String, String>,
+    multi_params: Vec<(String, String)>,
+    transport: QueryTransport,
+    options: RequestOptions,
 }
 
 impl<'a, 'b> Query<'a, 'b> {
     fn new(collection: &'b Collection) -> Query<'a, 'b> {
         Query {
-            collection: &collection,
-            params: HashMap::new()
+            collection,
+            params: HashMap::new(),
+            multi_params: vec![],
+            transport: QueryTransport::Get,
+            options: RequestOptions::default(),
         }
     }
 
-    /// Defines a query parameter.
+    /// Defines a query parameter. The value is percentage-encoded when the query is sent, so it
+    /// must not be encoded beforehand.
     ///
     /// # Arguments
     /// * `param` - The parameter name.
@@ -1189,6 +2294,18 @@ impl<'a, 'b> Query<'a, 'b> {
         self
     }
 
+    /// Adds a value of a query parameter which can be repeated (e.g. `facet.field`). Unlike
+    /// `set`, previous values of the parameter are kept.
+    ///
+    /// # Arguments
+    /// * `param` - The parameter name.
+    /// * `value` - The parameter value.
+    pub fn append<T>(&mut self, param: String, value: T) -> &mut Self
+        where T: std::string::ToString {
+        self.multi_params.push((param, value.to_string()));
+        self
+    }
+
     /// Sets the query string.
     ///
     /// # Arguments
@@ -1197,58 +2314,35 @@ impl<'a, 'b> Query<'a, 'b> {
     /// # See
     /// https://lucene.apache.org/solr/guide/8_5/the-standard-query-parser.html
     pub fn query(&mut self, query: &str) -> &mut Self {
-        let encoded = self.collection.client.url_encode(&query);
-        self.set("q".into(), encoded)
+        self.set("q".into(), query)
     }
 
-    fn query_json_impl(&mut self, json: &serde_json::Value) -> Result<String, SolrError> {
-        let mut str = String::new();
-        let field = json.get("field");
-
-        if field.is_some() {
-            let field = field.unwrap().as_str().unwrap();
-            let value = json.get("value");
-
-            if value.is_some() {
-                let value = value.unwrap();
-                str = format!("{}{}:{}", str, field, value);
-            } else {
-                // ERROR: Missing field value!
-                return Err(SolrError);
-            }
-        } else {
-            let mut op_name = "and";
-            let mut op = json.get(op_name);
-
-            if op.is_none() {
-                op_name = "or";
-                op = json.get(op_name);
-            }
-            if op.is_none() {
-                op_name = "neg";
-                op = json.get(op_name);
-            }
-
-            if op.is_some() {
-                let op = op.unwrap();
-
-                if op_name == "neg" {
-                    str = format!("{}!{}", str, self.query_json_impl(op).unwrap());
-                } else {
-                    let vec = op.as_array().unwrap().iter()
-                        .map(|v| self.query_json_impl(v).unwrap()).collect::<Vec<_>>();
-                    str = format!("{}({})", str, vec.join(if op_name == "and" { " AND " } else { " OR " }));
-                }
-            } else {
-                // ERROR: Invalid syntax! Expected an operation or a field.
-                return Err(SolrError);
-            }
-        }
-
-        Ok(str)
+    /// Sets the query string from a typed query expression.
+    ///
+    /// # Arguments
+    /// * `expr` -
+    ///
+    /// # Example
+    /// ```no_run
+    /// use solrdrv::dsl::Expr;
+    ///
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+    /// # let mut users = solr.collections().get("users".into()).await?;
+    /// let users_found = users.search()
+    ///     .query_expr(Expr::bool()
+    ///         .must(&Expr::phrase("name", "Some One"))
+    ///         .must_not(&Expr::term("age", 19)))
+    ///     .commit().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn query_expr(&mut self, expr: &dsl::Expr) -> &mut Self {
+        self.query(&expr.to_string())
     }
 
-    /// Sets the query string from a JSON-encoded query.
+    /// Sets the query string from a JSON-encoded query. The query is converted to a `dsl::Expr`,
+    /// so values are escaped: strings are matched as phrases, numbers and booleans as terms.
     ///
     /// # Arguments
     /// * `json` -
@@ -1276,9 +2370,10 @@ impl<'a, 'b> Query<'a, 'b> {
     /// ```
     ///
     /// # Example
-    /// Following is an example of how a query `(!(name:"Some" AND age:19) OR age:21)` would be
+    /// Following is an example of how a query `((*:* -(+name:"Some" +age:19)) OR age:21)` would be
     /// encoded in JSON.
-    /// ```
+    /// ```no_run
+    /// # use solrdrv::serde_json::json;
     /// let query = json!({
     ///     "or": [
     ///         {
@@ -1294,11 +2389,9 @@ impl<'a, 'b> Query<'a, 'b> {
     /// });
     /// ```
     pub fn query_json(&mut self, json: serde_json::Value) -> Result<&mut Self, SolrError> {
-        let query = match self.query_json_impl(&json) {
-            Ok(q) => q,
-            Err(e) => return Err(e)
-        };
-        println!("Query from JSON: {}", query);
+        let query = dsl::Expr::from_json(&json)?.to_string();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(query = %query, "Query from JSON");
         Ok(self.query(query.as_str()))
     }
 
@@ -1310,8 +2403,7 @@ impl<'a, 'b> Query<'a, 'b> {
     /// # See
     /// https://lucene.apache.org/solr/guide/8_5/common-query-parameters.html#deftype-parameter
     pub fn def_type(&mut self, def_type: String) -> &mut Self {
-        let encoded = self.collection.client.url_encode(&def_type);
-        self.set("defType".into(), encoded)
+        self.set("defType".into(), def_type)
     }
 
     /// Defines sorting of matching query results.
@@ -1322,8 +2414,7 @@ impl<'a, 'b> Query<'a, 'b> {
     /// # See
     /// https://lucene.apache.org/solr/guide/8_5/common-query-parameters.html#sort-parameter
     pub fn sort(&mut self, sort: String) -> &mut Self {
-        let encoded = self.collection.client.url_encode(&sort);
-        self.set("sort".into(), encoded)
+        self.set("sort".into(), sort)
     }
 
     /// Specifies an offset into a query's result set.
@@ -1349,7 +2440,7 @@ impl<'a, 'b> Query<'a, 'b> {
     }
 
     /// Defines a query that can be used to restrict the superset of documents that can be returned,
-    /// without influencing score.
+    /// without influencing score. Can be set multiple times, all filters must match.
     ///
     /// # Arguments
     /// * `fq`-
@@ -1357,8 +2448,7 @@ impl<'a, 'b> Query<'a, 'b> {
     /// # See
     /// https://lucene.apache.org/solr/guide/8_5/common-query-parameters.html#fq-filter-query-parameter
     pub fn fq(&mut self, fq: String) -> &mut Self {
-        let encoded = self.collection.client.url_encode(&fq);
-        self.set("fq".into(), encoded)
+        self.append("fq".into(), fq)
     }
 
     /// Limits document fields returned in a query's response.
@@ -1369,8 +2459,37 @@ impl<'a, 'b> Query<'a, 'b> {
     /// # See
     /// https://lucene.apache.org/solr/guide/8_5/common-query-parameters.html#fl-field-list-parameter
     pub fn fl(&mut self, fl: String) -> &mut Self {
-        let encoded = self.collection.client.url_encode(&fl);
-        self.set("fl".into(), encoded)
+        self.set("fl".into(), fl)
+    }
+
+    /// Returns child documents of each returned parent document using the `[child]` document
+    /// transformer. The transformer is appended to `fl`, which defaults to `*`.
+    ///
+    /// # Arguments
+    /// * `transformer` -
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use solrdrv::ChildTransformer;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+    /// # let mut orders = solr.collections().get("orders".into()).await?;
+    /// let orders = orders.search()
+    ///     .query("type:order")
+    ///     .child_docs(ChildTransformer::new().child_filter("sku:A*").limit(-1))
+    ///     .commit().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/transforming-result-documents.html#child-childdoctransformerfactory
+    pub fn child_docs(&mut self, transformer: &ChildTransformer) -> &mut Self {
+        let fl = match self.params.get("fl") {
+            Some(fl) => format!("{},{}", fl, transformer.build()),
+            None => format!("*,{}", transformer.build()),
+        };
+        self.set("fl".into(), fl)
     }
 
     /// Specifies debug info returned in a query's response.
@@ -1381,8 +2500,7 @@ impl<'a, 'b> Query<'a, 'b> {
     /// # See
     /// https://lucene.apache.org/solr/guide/8_5/common-query-parameters.html#debug-parameter
     pub fn debug(&mut self, debug: String) -> &mut Self {
-        let encoded = self.collection.client.url_encode(&debug);
-        self.set("debug".into(), encoded)
+        self.set("debug".into(), debug)
     }
 
     /// Specifies a Lucene query in order to identify a set of documents.
@@ -1393,8 +2511,7 @@ impl<'a, 'b> Query<'a, 'b> {
     /// # See
     /// https://lucene.apache.org/solr/guide/8_5/common-query-parameters.html#explainother-parameter
     pub fn explain_other(&mut self, explain_other: String) -> &mut Self {
-        let encoded = self.collection.client.url_encode(&explain_other);
-        self.set("explainOther".into(), encoded)
+        self.set("explainOther".into(), explain_other)
     }
 
     /// Specifies the amount of time (in milliseconds) allowed for a search to complete.
@@ -1439,8 +2556,7 @@ impl<'a, 'b> Query<'a, 'b> {
     /// # See
     /// https://lucene.apache.org/solr/guide/8_5/common-query-parameters.html#wt-parameter
     pub fn wt(&mut self, wt: String) -> &mut Self {
-        let encoded = self.collection.client.url_encode(&wt);
-        self.set("wt".into(), encoded)
+        self.set("wt".into(), wt)
     }
 
     /// Enables caching of query results.
@@ -1462,8 +2578,7 @@ impl<'a, 'b> Query<'a, 'b> {
     /// # See
     /// https://lucene.apache.org/solr/guide/8_5/common-query-parameters.html#logparamslist-parameter
     pub fn log_params_list(&mut self, log_params_list: String) -> &mut Self {
-        let encoded = self.collection.client.url_encode(&log_params_list);
-        self.set("logParamsList".into(), encoded)
+        self.set("logParamsList".into(), log_params_list)
     }
 
     /// Controls what information about request parameters is included in the response header.
@@ -1474,43 +2589,927 @@ impl<'a, 'b> Query<'a, 'b> {
     /// # See
     /// https://lucene.apache.org/solr/guide/8_5/common-query-parameters.html#echoparams-parameter
     pub fn echo_params(&mut self, echo_params: String) -> &mut Self {
-        let encoded = self.collection.client.url_encode(&echo_params);
-        self.set("echoParams".into(), encoded)
+        self.set("echoParams".into(), echo_params)
     }
 
-    fn build_path(&self) -> String {
+    /// Enables faceting.
+    ///
+    /// # Arguments
+    /// * `facet` -
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/faceting.html
+    pub fn facet(&mut self, facet: bool) -> &mut Self {
+        self.set("facet".into(), facet)
+    }
+
+    /// Adds a field facet and enables faceting.
+    ///
+    /// # Arguments
+    /// * `facet` -
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/faceting.html#field-value-faceting-parameters
+    pub fn facet_field(&mut self, facet: &FieldFacet) -> &mut Self {
+        for (param, value) in facet.to_params() {
+            self.append(param, value);
+        }
+        self.facet(true)
+    }
+
+    /// Adds a facet query, counting documents matching the query, and enables faceting.
+    ///
+    /// # Arguments
+    /// * `query` -
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/faceting.html#facet-query-parameter
+    pub fn facet_query(&mut self, query: &str) -> &mut Self {
+        self.append("facet.query".into(), query);
+        self.facet(true)
+    }
+
+    /// Adds a range facet and enables faceting.
+    ///
+    /// # Arguments
+    /// * `facet` -
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/faceting.html#range-faceting
+    pub fn facet_range(&mut self, facet: &RangeFacet) -> &mut Self {
+        for (param, value) in facet.to_params() {
+            self.append(param, value);
+        }
+        self.facet(true)
+    }
+
+    /// Adds a pivot (decision tree) facet and enables faceting.
+    ///
+    /// # Arguments
+    /// * `fields` - Comma separated field names, e.g. `category,brand`.
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/faceting.html#pivot-decision-tree-faceting
+    pub fn facet_pivot(&mut self, fields: &str) -> &mut Self {
+        self.append("facet.pivot".into(), fields);
+        self.facet(true)
+    }
+
+    /// Adds an interval facet and enables faceting.
+    ///
+    /// # Arguments
+    /// * `facet` -
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/faceting.html#interval-faceting
+    pub fn facet_interval(&mut self, facet: &IntervalFacet) -> &mut Self {
+        for (param, value) in facet.to_params() {
+            self.append(param, value);
+        }
+        self.facet(true)
+    }
+
+    /// Adds a facet of the JSON Facet API.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the facet in the result.
+    /// * `facet` -
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use solrdrv::JsonFacet;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+    /// # let mut products = solr.collections().get("products".into()).await?;
+    /// let response = products.search()
+    ///     .json_facet("brands", JsonFacet::terms("brand").limit(5))
+    ///     .execute().await?;
+    /// let brands = &response.facets.unwrap().facets["brands"];
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/json-facet-api.html
+    pub fn json_facet(&mut self, name: &str, facet: &JsonFacet) -> &mut Self {
+        self.add_json_facet(name, facet.build())
+    }
+
+    /// Adds a stat aggregation over all matching documents using the JSON Facet API.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the stat in the result.
+    /// * `aggregation` - An aggregation function (see `json_facet::avg`, ...).
+    pub fn json_facet_stat(&mut self, name: &str, aggregation: &str) -> &mut Self {
+        self.add_json_facet(name, json!(aggregation))
+    }
+
+    fn add_json_facet(&mut self, name: &str, facet: Value) -> &mut Self {
+        let mut facets = self.params.get("json.facet")
+            .and_then(|f| serde_json::from_str::<serde_json::Map<String, Value>>(f).ok())
+            .unwrap_or_default();
+        facets.insert(name.to_string(), facet);
+        self.set("json.facet".into(), Value::Object(facets))
+    }
+
+    /// Groups the results by a field, a function or queries. The groups are returned in
+    /// `QueryResponse::grouped`, unless `Grouping::main` is set.
+    ///
+    /// # Arguments
+    /// * `grouping` -
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/result-grouping.html
+    pub fn group(&mut self, grouping: &group::Grouping) -> &mut Self {
+        for (param, value) in grouping.to_params() {
+            match param.as_str() {
+                "group.field" | "group.query" | "group.func" => self.append(param, value),
+                _ => self.set(param, value),
+            };
+        }
+        self.set("group".into(), true)
+    }
+
+    /// Collapses the results, keeping a single head document per value of a field. Added as a
+    /// filter query, in addition to `fq`.
+    ///
+    /// # Arguments
+    /// * `collapse` -
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/collapse-and-expand-results.html
+    pub fn collapse(&mut self, collapse: &group::Collapse) -> &mut Self {
+        self.append("fq".into(), collapse.build())
+    }
+
+    /// Returns the documents collapsed into each head document (see `collapse`) in
+    /// `QueryResponse::expanded`.
+    ///
+    /// # Arguments
+    /// * `expand` -
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/collapse-and-expand-results.html#expand-component
+    pub fn expand(&mut self, expand: &group::Expand) -> &mut Self {
+        for (param, value) in expand.to_params() {
+            match param.as_str() {
+                "expand.fq" => self.append(param, value),
+                _ => self.set(param, value),
+            };
+        }
+        self.set("expand".into(), true)
+    }
+
+    /// Enables spellchecking of the query. Suggestions are returned in
+    /// `QueryResponse::spellcheck`.
+    ///
+    /// # Arguments
+    /// * `spellcheck` -
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use solrdrv::Spellcheck;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+    /// # let mut products = solr.collections().get("products".into()).await?;
+    /// let response = products.search()
+    ///     .query("name:delll")
+    ///     .spellcheck(Spellcheck::new().collate(true).max_collation_tries(5).collate_extended_results(true))
+    ///     .execute().await?;
+    /// if response.num_found == 0 {
+    ///     if let Some(query) = response.did_you_mean() {
+    ///         println!("Did you mean {}?", query);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/spell-checking.html
+    pub fn spellcheck(&mut self, spellcheck: &Spellcheck) -> &mut Self {
+        for (param, value) in spellcheck.to_params() {
+            match param.as_str() {
+                "spellcheck.dictionary" => self.append(param, value),
+                _ => self.set(param, value),
+            };
+        }
+        self.set("spellcheck".into(), true)
+    }
+
+    /// Enables highlighting of matching terms in the returned documents.
+    ///
+    /// # Arguments
+    /// * `highlight` -
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/highlighting.html
+    pub fn highlight(&mut self, highlight: &Highlight) -> &mut Self {
+        for (param, value) in highlight.to_params() {
+            self.set(param, value);
+        }
+        self.set("hl".into(), true)
+    }
+
+    /// Enables the stats component, computing statistics of fields over the matching documents.
+    ///
+    /// # Arguments
+    /// * `stats` -
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use solrdrv::{Stats, StatsField};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+    /// # let mut products = solr.collections().get("products".into()).await?;
+    /// let response = products.search()
+    ///     .query("*:*")
+    ///     .fq("{!tag=brand}brand:acme".into())
+    ///     .stats(Stats::new().field(StatsField::new("price").percentiles(&[50.0, 90.0]).exclude("brand")))
+    ///     .rows(0)
+    ///     .execute().await?;
+    /// let price = &response.stats.unwrap()["price"];
+    /// println!("{:?} - {:?}, median {:?}", price.min, price.max, price.percentiles.first());
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/the-stats-component.html
+    pub fn stats(&mut self, stats: &Stats) -> &mut Self {
+        for (param, value) in stats.to_params() {
+            self.append(param, value);
+        }
+        self.set("stats".into(), true)
+    }
+
+    /// Overrides the client's authentication for the query.
+    ///
+    /// # Arguments
+    /// * `auth` -
+    pub fn auth(&mut self, auth: Auth) -> &mut Self {
+        self.options.auth(auth);
+        self
+    }
+
+    /// Sets a header sent with the query.
+    ///
+    /// # Arguments
+    /// * `name` - The header name.
+    /// * `value` - The header value.
+    pub fn header(&mut self, name: String, value: String) -> &mut Self {
+        self.options.header(name, value);
+        self
+    }
+
+    /// Sets the transport of the query. Defaults to `QueryTransport::Get`.
+    ///
+    /// # Arguments
+    /// * `transport` -
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/json-request-api.html
+    pub fn transport(&mut self, transport: QueryTransport) -> &mut Self {
+        self.transport = transport;
+        self
+    }
+
+    fn build_path_from(&self, params: &HashMap<String, String>) -> String {
         let mut path: String = format!("{}/select?", self.collection.name);
-        for (k, v) in self.params.iter() {
-            path = format!("{}{}={}&", path, k, v);
+        for (k, v) in params.iter().chain(self.multi_params.iter().map(|(k, v)| (k, v))) {
+            path = format!("{}{}={}&", path, k, self.collection.client.url_encode(v));
         }
         path.remove(path.len() - 1);
         path
     }
 
-    /// Commits the query and returns its result.
+    /// Returns a JSON Request API body of the query. Parameters without a JSON counterpart are
+    /// passed in `params`.
+    fn build_json_from(&self, params: &HashMap<String, String>) -> serde_json::Value {
+        let mut body = serde_json::Map::new();
+        let mut filters = vec![];
+        let mut other = serde_json::Map::new();
+        for (k, v) in params.iter().chain(self.multi_params.iter().map(|(k, v)| (k, v))) {
+            match (k.as_str(), v.parse::<u64>()) {
+                ("q", _) => { body.insert("query".into(), json!(v)); },
+                ("fq", _) => filters.push(json!(v)),
+                ("fl", _) => { body.insert("fields".into(), json!(v)); },
+                ("sort", _) => { body.insert("sort".into(), json!(v)); },
+                ("start", Ok(offset)) => { body.insert("offset".into(), json!(offset)); },
+                ("rows", Ok(limit)) => { body.insert("limit".into(), json!(limit)); },
+                ("json.facet", _) => match serde_json::from_str::<Value>(v) {
+                    Ok(facet) => { body.insert("facet".into(), facet); },
+                    Err(_) => push_param(&mut other, k, v),
+                },
+                _ => push_param(&mut other, k, v),
+            }
+        }
+        if !filters.is_empty() {
+            body.insert("filter".into(), Value::Array(filters));
+        }
+        if !other.is_empty() {
+            body.insert("params".into(), Value::Object(other));
+        }
+        Value::Object(body)
+    }
+
+    /// Sends the query with the given parameters using the query's transport and returns the URL
+    /// of the request with the response.
+    async fn fetch(&self, params: &HashMap<String, String>) -> Result<(String, serde_json::Value), SolrError> {
+        let path = self.build_path_from(params);
+        let url = self.collection.client.format_url(&path);
+        if self.transport == QueryTransport::Get && url.len() <= MAX_GET_URL_LENGTH {
+            let res = self.collection.client.get_with(&path, &self.options).await?;
+            return Ok((url, res));
+        }
+        #[cfg(feature = "tracing")]
+        if self.transport == QueryTransport::Get {
+            tracing::debug!(collection = %self.collection.name, url_length = url.len(), "Query URL too long, using JSON Request API");
+        }
+        let path = format!("{}/query", self.collection.name);
+        let url = self.collection.client.format_url(&path);
+        let res = self.collection.client.post_with(&path, &self.build_json_from(params), &self.options).await?;
+        Ok((url, res))
+    }
+
+    /// Returns parameters of the first page of a cursor, i.e. the query parameters with a sort
+    /// ending with the collection's unique key.
+    async fn cursor_params(&self) -> Result<HashMap<String, String>, SolrError> {
+        let unique_key = self.collection.schema().unique_key_with(&self.options).await?;
+        first_cursor_params(&self.params, &unique_key)
+    }
+
+    /// Returns a stream of all documents matching the query, fetched page by page using a cursor
+    /// (`cursorMark`). Only one page of documents is held in memory at a time. The size of a page
+    /// is given by `rows` (defaults to 100).
+    ///
+    /// If `sort` does not include the collection's unique key, then it is appended as a
+    /// tie-breaker (e.g. `name asc` becomes `name asc,id asc`).
     ///
     /// # Example
+    /// ```no_run
+    /// use solrdrv::futures::TryStreamExt;
+    ///
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+    /// # let mut users = solr.collections().get("users".into()).await?;
+    /// let mut query = users.search();
+    /// query.query("*:*")
+    ///     .sort("name asc".into())
+    ///     .rows(1000);
+    /// let mut docs = query.stream();
+    /// while let Some(doc) = docs.try_next().await? {
+    ///     println!("{}", doc);
+    /// }
+    /// # Ok(())
+    /// # }
     /// ```
+    ///
+    /// # Return
+    /// If `start` is set to a non-zero value, then the stream yields `SolrError::InvalidInput`.
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/pagination-of-results.html#fetching-a-large-number-of-sorted-results-cursors
+    pub fn stream(&self) -> BoxStream<'_, Result<serde_json::Value, SolrError>> {
+        stream::try_unfold(CursorState::Start, move |state| async move {
+            let (mut params, cursor_mark) = match state {
+                CursorState::Start => (self.cursor_params().await?, "*".to_string()),
+                CursorState::Next(params, cursor_mark) => (params, cursor_mark),
+                CursorState::Done => return Ok(None),
+            };
+            params.insert("cursorMark".into(), cursor_mark.clone());
+            let (url, res) = self.fetch(&params).await?;
+
+            let page = QueryResponse::parse(&url, res)?;
+            let docs = page.docs;
+            let next = match page.next_cursor_mark {
+                Some(next) => next,
+                None => return Err(SolrError::UnexpectedResponse {
+                    url,
+                    message: "missing `nextCursorMark`".into(),
+                }),
+            };
+            #[cfg(feature = "tracing")]
+            tracing::debug!(collection = %self.collection.name, docs = docs.len(), "Fetched cursor page");
+
+            let state = CursorState::after_page(params, &cursor_mark, next, docs.is_empty());
+            Ok(Some((docs, state)))
+        })
+        .map_ok(|docs| stream::iter(docs.into_iter().map(Ok)))
+        .try_flatten()
+        .boxed()
+    }
+
+    /// Commits the query and returns its full response, including the number of matching
+    /// documents, the response header and sections of search components (facets, highlighting,
+    /// stats, ...).
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+    /// # let mut users = solr.collections().get("users".into()).await?;
+    /// let page = users.search()
+    ///     .query("name:Some")
+    ///     .start(20)
+    ///     .rows(10)
+    ///     .execute().await?;
+    /// println!("Showing {} of {} users", page.docs.len(), page.num_found);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn execute(&self) -> Result<QueryResponse, SolrError> {
+        let (url, res) = self.fetch(&self.params).await?;
+        let response = QueryResponse::parse(&url, res)?;
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            collection = %self.collection.name,
+            docs = response.docs.len(),
+            num_found = response.num_found,
+            "Query returned documents");
+        Ok(response)
+    }
+
+    /// Same as `execute`, but deserializes the returned documents into type `T`.
+    pub async fn execute_as<T>(&self) -> Result<QueryResponse<T>, SolrError>
+        where T: DeserializeOwned {
+        self.execute().await?.into_typed()
+    }
+
+    /// Commits the query and returns its result deserialized into type `T`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use serde::Deserialize;
+    /// #[derive(Deserialize)]
+    /// struct User {
+    ///     name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+    /// # let mut users = solr.collections().get("users".into()).await?;
+    /// let users_found: Vec<User> = users.search()
+    ///     .query("name:Some")
+    ///     .commit_as().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn commit_as<T>(&self) -> Result<Vec<T>, SolrError>
+        where T: DeserializeOwned {
+        Ok(self.execute_as().await?.docs)
+    }
+
+    /// Commits the query and returns its result, each document paired with its highlighted
+    /// snippets (see `highlight`). The collection's unique key must be returned in `fl`. It is
+    /// looked up using the schema API, to avoid the extra request use
+    /// `execute().await?.highlighted(unique_key)` instead.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use solrdrv::Highlight;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+    /// # let mut articles = solr.collections().get("articles".into()).await?;
+    /// let found = articles.search()
+    ///     .query("body:solr")
+    ///     .highlight(Highlight::new().fields("title,body").snippets(3).tags("<b>", "</b>"))
+    ///     .commit_highlighted().await?;
+    /// for article in found {
+    ///     println!("{}: {:?}", article.doc["title"], article.snippets.get("body"));
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn commit_highlighted(&self) -> Result<Vec<Highlighted>, SolrError> {
+        let unique_key = self.collection.schema().unique_key_with(&self.options).await?;
+        Ok(self.execute().await?.highlighted(&unique_key))
+    }
+
+    /// Same as `commit_highlighted`, but deserializes the returned documents into type `T`.
+    pub async fn commit_highlighted_as<T>(&self) -> Result<Vec<Highlighted<T>>, SolrError>
+        where T: DeserializeOwned {
+        self.commit_highlighted().await?
+            .into_iter()
+            .map(|h| Ok(Highlighted { doc: serde_json::from_value(h.doc)?, snippets: h.snippets }))
+            .collect()
+    }
+
+    /// Commits the query and returns its result.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+    /// # let mut users = solr.collections().get("users".into()).await?;
     /// let users_found = users.search()
     ///     .query("name:Some")
-    ///     .sort("age asc")
-    ///     .fl("name,age")
+    ///     .sort("age asc".into())
+    ///     .fl("name,age".into())
     ///     .commit().await?;
+    /// # Ok(())
+    /// # }
     /// ```
     pub async fn commit(&self) -> Result<Vec<serde_json::Value>, SolrError> {
-        let path = self.build_path();
-        let res = match self.collection.client.get(&path).await {
-            Ok(r) => r,
-            Err(_) => return Err(SolrError),
-        };
-        Ok(res["response"]["docs"].as_array().unwrap().clone())
+        Ok(self.execute().await?.docs)
+    }
+}
+
+/// Adds a value of a parameter to `params` of a JSON Request API body, turning repeated
+/// parameters into arrays.
+fn push_param(params: &mut serde_json::Map<String, Value>, name: &str, value: &str) {
+    match params.get_mut(name) {
+        Some(Value::Array(values)) => values.push(json!(value)),
+        Some(previous) => *previous = json!([previous.take(), value]),
+        None => { params.insert(name.to_string(), json!(value)); },
+    }
+}
+
+/// Returns parameters of the first page of a cursor: `start` is removed (a non-zero `start` is
+/// rejected), the unique key is appended to `sort` as a tie-breaker unless already present and
+/// `rows` defaults to `CURSOR_DEFAULT_ROWS`.
+///
+/// # Arguments
+/// * `params` - The query parameters.
+/// * `unique_key` - The unique key field of the collection.
+fn first_cursor_params(params: &HashMap<String, String>, unique_key: &str) -> Result<HashMap<String, String>, SolrError> {
+    let mut params = params.clone();
+    match params.remove("start") {
+        Some(start) if start != "0" => {
+            return Err(SolrError::InvalidInput(
+                format!("cursors cannot be combined with `start={}`", start)));
+        },
+        _ => {},
+    }
+
+    let sort = params.remove("sort").unwrap_or_default();
+    let has_tie_breaker = sort.split(',')
+        .any(|clause| clause.split_whitespace().next() == Some(unique_key));
+    let sort = if sort.trim().is_empty() {
+        format!("{} asc", unique_key)
+    } else if !has_tie_breaker {
+        format!("{},{} asc", sort, unique_key)
+    } else {
+        sort
+    };
+    params.insert("sort".into(), sort);
+    params.entry("rows".into()).or_insert_with(|| CURSOR_DEFAULT_ROWS.to_string());
+    Ok(params)
+}
+
+#[derive(Debug, PartialEq)]
+/// A state of a cursor used by `Query::stream`
+enum CursorState {
+    Start,
+    Next(HashMap<String, String>, String),
+    Done,
+}
+
+impl CursorState {
+    /// Returns the state after a page was fetched. Paging stops on an empty page or when the
+    /// cursor mark did not change, i.e. all documents were returned.
+    ///
+    /// # Arguments
+    /// * `params` - The cursor parameters.
+    /// * `cursor_mark` - The cursor mark of the fetched page.
+    /// * `next` - The `nextCursorMark` of the fetched page.
+    /// * `empty` - Whether the fetched page has no documents.
+    fn after_page(params: HashMap<String, String>, cursor_mark: &str, next: String, empty: bool) -> CursorState {
+        if empty || next == cursor_mark {
+            CursorState::Done
+        } else {
+            CursorState::Next(params, next)
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+
+    #[test]
+    fn solr_builder_defaults_and_overrides() {
+        let builder = Solr::builder("https".into(), "solr.local".into(), 8984);
+        assert_eq!(builder.pool_idle_timeout, None);
+        assert_eq!(builder.pool_max_idle_per_host, None);
+        assert_eq!(builder.tcp_keepalive, None);
+        assert_eq!(builder.connect_timeout, None);
+        assert_eq!(builder.timeout, None);
+        assert_eq!(builder.user_agent, None);
+        assert!(builder.auth.is_none());
+        assert!(builder.headers.is_empty());
+
+        let mut builder = Solr::builder("https".into(), "solr.local".into(), 8984);
+        builder.pool_idle_timeout(Duration::from_secs(30))
+            .pool_max_idle_per_host(32)
+            .tcp_keepalive(Duration::from_secs(60))
+            .connect_timeout(Duration::from_secs(5))
+            .timeout(Duration::from_secs(120))
+            .user_agent("indexer/1.0".into())
+            .bearer_auth("token".into())
+            .header("X-Tenant".into(), "acme".into());
+        assert_eq!(builder.pool_idle_timeout, Some(Duration::from_secs(30)));
+        assert_eq!(builder.pool_max_idle_per_host, Some(32));
+        assert_eq!(builder.tcp_keepalive, Some(Duration::from_secs(60)));
+        assert_eq!(builder.connect_timeout, Some(Duration::from_secs(5)));
+        assert_eq!(builder.timeout, Some(Duration::from_secs(120)));
+        assert_eq!(builder.user_agent.as_deref(), Some("indexer/1.0"));
+        assert_eq!(builder.headers["X-Tenant"], "acme");
+
+        let solr = builder.build().unwrap();
+        assert!(matches!(&solr.auth, Some(Auth::Bearer(token)) if token == "token"));
+        assert_eq!(solr.format_url("users/select?q=*:*"), "https://solr.local:8984/solr/users/select?q=*:*");
+
+        let err = Solr::builder("http".into(), "localhost".into(), 8983)
+            .header("Bad Header".into(), "x".into())
+            .build().unwrap_err();
+        assert!(matches!(err, SolrError::InvalidInput(_)));
+    }
+
+    #[test]
+    fn request_options_override_client_auth() {
+        let header = |req: reqwest::Request, name: &str| req.headers().get(name)
+            .map(|value| value.to_str().unwrap_or_default().to_string());
+        let solr = Solr::builder("http".into(), "localhost".into(), 8983)
+            .basic_auth("solr".into(), Some("SolrRocks".into()))
+            .build().unwrap();
+
+        let req = solr.apply_options(reqwest::Client::new().get("http://localhost/"), &RequestOptions::default());
+        assert_eq!(header(req.build().unwrap(), "authorization").as_deref(), Some("Basic c29scjpTb2xyUm9ja3M="));
+
+        let mut options = RequestOptions::default();
+        options.auth(Auth::Bearer("token".into())).header("X-Tenant".into(), "acme".into());
+        let req = solr.apply_options(reqwest::Client::new().get("http://localhost/"), &options).build().unwrap();
+        assert_eq!(header(req.try_clone().unwrap(), "authorization").as_deref(), Some("Bearer token"));
+        assert_eq!(header(req, "x-tenant").as_deref(), Some("acme"));
+
+        let req = Solr::client("http".into(), "localhost".into(), 8983)
+            .apply_options(reqwest::Client::new().get("http://localhost/"), &RequestOptions::default());
+        assert_eq!(header(req.build().unwrap(), "authorization"), None);
+    }
+
+    #[tokio::test]
+    async fn request_options_override_client_headers() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // A server capturing the head of a single request
+        let mut listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut head = vec![];
+            let mut buf = [0; 1024];
+            while !head.ends_with(b"\r\n\r\n") {
+                let n = socket.read(&mut buf).await.unwrap();
+                head.extend_from_slice(&buf[..n]);
+            }
+            socket.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\n{}").await.unwrap();
+            String::from_utf8_lossy(&head).to_lowercase()
+        });
+
+        let solr = Solr::builder("http".into(), "127.0.0.1".into(), port)
+            .header("X-Tenant".into(), "default".into())
+            .build().unwrap();
+        let mut options = RequestOptions::default();
+        options.header("X-Tenant".into(), "override".into());
+        solr.get_with("admin/info/system", &options).await.unwrap();
+
+        let head = server.await.unwrap();
+        assert!(head.contains("x-tenant: override\r\n"));
+        assert!(!head.contains("x-tenant: default"));
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    async fn request_span_records_fields() {
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+
+        // A subscriber recording the fields of `solr.request` spans
+        struct Recorder(Arc<Mutex<Vec<(String, String)>>>);
+
+        impl Visit for Recorder {
+            fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                self.0.lock().unwrap().push((field.name().into(), format!("{:?}", value)));
+            }
+        }
+
+        impl tracing::Subscriber for Recorder {
+            fn enabled(&self, _: &tracing::Metadata) -> bool { true }
+            fn new_span(&self, span: &Attributes) -> Id {
+                if span.metadata().name() == "solr.request" {
+                    span.record(&mut Recorder(self.0.clone()));
+                }
+                Id::from_u64(1)
+            }
+            fn record(&self, _: &Id, _: &Record) {}
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, _: &tracing::Event) {}
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+
+        let fields = Arc::new(Mutex::new(vec![]));
+        let _guard = tracing::subscriber::set_default(Recorder(fields.clone()));
+        let solr = Solr::builder("http".into(), "127.0.0.1".into(), 1).build().unwrap();
+        assert!(solr.get("admin/info/system").await.is_err());
+
+        let fields = fields.lock().unwrap();
+        assert_eq!(*fields, vec![
+            ("method".to_string(), "\"GET\"".to_string()),
+            ("url".to_string(), "http://127.0.0.1:1/solr/admin/info/system".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn solr_error_from_payload() {
+        let payload = json!({
+            "metadata": [
+                "error-class", "org.apache.solr.common.SolrException",
+                "root-error-class", "org.apache.solr.common.SolrException"
+            ],
+            "msg": "undefined field foo",
+            "code": 400
+        });
+        let err = SolrError::from_payload("http://localhost:8983/solr/users/select".into(), 400, &payload);
+        match &err {
+            SolrError::Solr { msg, code, metadata, .. } => {
+                assert_eq!(msg, "undefined field foo");
+                assert_eq!(*code, Some(400));
+                assert_eq!(metadata["error-class"], "org.apache.solr.common.SolrException");
+            },
+            _ => panic!("expected SolrError::Solr, got {:?}", err),
+        }
+        assert_eq!(err.status(), Some(400));
+        assert_eq!(err.url(), Some("http://localhost:8983/solr/users/select"));
+    }
+
+
+    #[test]
+    fn response_body_classification() {
+        let url = || "http://localhost/solr/users/update".to_string();
+        let conflict = r#"{"error":{"code":409,"msg":"version conflict for 1 expected=5 actual=6"}}"#;
+        match Solr::parse_response_body(url(), reqwest::StatusCode::CONFLICT, conflict.into()) {
+            Err(SolrError::VersionConflict { msg, .. }) => assert_eq!(msg, "version conflict for 1 expected=5 actual=6"),
+            res => panic!("expected a version conflict, got {:?}", res),
+        }
+        match Solr::parse_response_body(url(), reqwest::StatusCode::CONFLICT, "Conflict".into()) {
+            Err(SolrError::VersionConflict { msg, .. }) => assert_eq!(msg, "Conflict"),
+            res => panic!("expected a version conflict, got {:?}", res),
+        }
+        let ok = r#"{"responseHeader":{"status":0,"QTime":1}}"#;
+        let json = Solr::parse_response_body(url(), reqwest::StatusCode::OK, ok.into()).unwrap();
+        assert_eq!(json["responseHeader"]["status"], json!(0));
+        match Solr::parse_response_body(url(), reqwest::StatusCode::BAD_GATEWAY, "<html>".into()) {
+            Err(SolrError::Http { status: 502, body, .. }) => assert_eq!(body, "<html>"),
+            res => panic!("expected an HTTP error, got {:?}", res),
+        }
+    }
+
+    #[test]
+    fn add_versioned_sets_version() {
+        let solr = Solr::client("http".into(), "localhost".into(), 8983);
+        let mut users = Collection::new(&solr, "users".into());
+        users.add_versioned(json!({ "id": "1", "name": "Some" }), -1);
+        match &users.commands_to_commit[..] {
+            [UpdateCommand::Add(doc)] => assert_eq!(doc, &json!({ "id": "1", "name": "Some", "_version_": -1 })),
+            _ => panic!("expected a single add command"),
+        }
+        users.add_versioned(json!("not a document"), 1);
+        assert!(matches!(users.error, Some(SolrError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn cursor_params_and_paging() {
+        let params = |pairs: &[(&str, &str)]| pairs.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<HashMap<_, _>>();
+
+        let first = first_cursor_params(&params(&[("q", "*:*"), ("start", "0")]), "id").unwrap();
+        assert_eq!(first, params(&[("q", "*:*"), ("sort", "id asc"), ("rows", "100")]));
+        let first = first_cursor_params(&params(&[("sort", "name asc"), ("rows", "10")]), "id").unwrap();
+        assert_eq!(first["sort"], "name asc,id asc");
+        assert_eq!(first["rows"], "10");
+        let first = first_cursor_params(&params(&[("sort", "name asc, id desc")]), "id").unwrap();
+        assert_eq!(first["sort"], "name asc, id desc");
+        // A field merely prefixed by the unique key is not a tie-breaker
+        let first = first_cursor_params(&params(&[("sort", "id_alt asc")]), "id").unwrap();
+        assert_eq!(first["sort"], "id_alt asc,id asc");
+        assert!(matches!(first_cursor_params(&params(&[("start", "20")]), "id"), Err(SolrError::InvalidInput(_))));
+
+        assert_eq!(CursorState::after_page(HashMap::new(), "*", "AoE1".into(), false),
+            CursorState::Next(HashMap::new(), "AoE1".into()));
+        assert_eq!(CursorState::after_page(HashMap::new(), "AoE1", "AoE1".into(), false), CursorState::Done);
+        assert_eq!(CursorState::after_page(HashMap::new(), "*", "AoE1".into(), true), CursorState::Done);
+    }
+
+    #[test]
+    fn update_command_body() {
+        let body = UpdateCommand::to_body(&[
+            UpdateCommand::Add(json!({ "id": "1" })),
+            UpdateCommand::DeleteById("2".into()),
+            UpdateCommand::DeleteByQuery("age:[* TO 18}".into()),
+            UpdateCommand::Add(json!({ "id": "3" })),
+        ]).unwrap();
+        assert_eq!(body, concat!(
+            r#"{"add":{"doc":{"id":"1"}},"delete":{"id":"2"},"#,
+            r#""delete":{"query":"age:[* TO 18}"},"add":{"doc":{"id":"3"}}}"#));
+    }
+
+
+
+
+
+    #[test]
+    fn query_set_is_encoded_once() {
+        let solr = Solr::client("http".into(), "localhost".into(), 8983);
+        let users = Collection::new(&solr, "users".into());
+        let mut query = users.search();
+        query.set("q".into(), "name:a b");
+        assert_eq!(query.build_path_from(&query.params), "users/select?q=name%3Aa%20b");
+    }
+
+    #[test]
+    fn query_fq_is_repeated() {
+        let solr = Solr::client("http".into(), "localhost".into(), 8983);
+        let users = Collection::new(&solr, "users".into());
+        let mut query = users.search();
+        query.fq("age:[18 TO *]".into()).fq("{!tag=c}country:FR".into());
+        assert_eq!(query.build_path_from(&query.params),
+            "users/select?fq=age%3A%5B18%20TO%20%2A%5D&fq=%7B%21tag%3Dc%7Dcountry%3AFR");
+    }
+
+    #[test]
+    fn query_group_and_expand_params() {
+        let solr = Solr::client("http".into(), "localhost".into(), 8983);
+        let variants = Collection::new(&solr, "variants".into());
+        let mut query = variants.search();
+        query.expand(group::Expand::new().fq("a:1").fq("b:2"));
+        assert_eq!(query.build_path_from(&query.params), "variants/select?expand=true&expand.fq=a%3A1&expand.fq=b%3A2");
+
+        let mut query = variants.search();
+        query.group(group::Grouping::new().field("product_id").limit(3))
+            .group(group::Grouping::new().field("color").limit(5));
+        assert_eq!(query.params["group.limit"], "5");
+        assert_eq!(query.multi_params, vec![
+            ("group.field".to_string(), "product_id".to_string()),
+            ("group.field".to_string(), "color".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn query_spellcheck_params() {
+        let solr = Solr::client("http".into(), "localhost".into(), 8983);
+        let products = Collection::new(&solr, "products".into());
+        let mut query = products.search();
+        query.spellcheck(Spellcheck::new().dictionary("default").count(5))
+            .spellcheck(Spellcheck::new().dictionary("wordbreak").count(10));
+        assert_eq!(query.params["spellcheck.count"], "10");
+        assert_eq!(query.multi_params, vec![
+            ("spellcheck.dictionary".to_string(), "default".to_string()),
+            ("spellcheck.dictionary".to_string(), "wordbreak".to_string()),
+        ]);
+    }
+
+
+
+
+
+
+    #[test]
+    fn json_request_body() {
+        let solr = Solr::client("http".into(), "localhost".into(), 8983);
+        let products = Collection::new(&solr, "products".into());
+        let mut query = products.search();
+        query.query("name:phone")
+            .fq("brand:acme".into())
+            .fq("{!tag=p}price:[* TO 100]".into())
+            .fl("id,name".into())
+            .start(10)
+            .rows(5)
+            .json_facet("brands", JsonFacet::terms("brand").limit(3))
+            .set("defType".into(), "edismax")
+            .append("bq".into(), "a")
+            .append("bq".into(), "b");
+        assert_eq!(query.build_json_from(&query.params), json!({
+            "query": "name:phone",
+            "filter": ["brand:acme", "{!tag=p}price:[* TO 100]"],
+            "fields": "id,name",
+            "offset": 10,
+            "limit": 5,
+            "facet": { "brands": { "type": "terms", "field": "brand", "limit": 3 } },
+            "params": { "defType": "edismax", "bq": ["a", "b"] }
+        }));
+    }
+
+
+
+
 }
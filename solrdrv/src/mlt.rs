@@ -0,0 +1,405 @@
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use crate::response::named_list;
+use crate::{local_param_value, Auth, Collection, QueryResponse, RequestOptions, SolrError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A transport of a MoreLikeThis request
+pub enum MltMode {
+    /// Uses the `/mlt` request handler, which supports text sources and interesting terms.
+    Handler,
+    /// Uses the `{!mlt}` query parser on the `/select` handler, which works in SolrCloud without
+    /// configuring a handler, but only supports document sources.
+    QueryParser,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A format of interesting terms returned by the `/mlt` handler
+pub enum InterestingTerms {
+    /// Returns no interesting terms.
+    None,
+    /// Returns the terms.
+    List,
+    /// Returns the terms with their boosts.
+    Details,
+}
+
+impl InterestingTerms {
+    /// Returns the value of the `mlt.interestingTerms` parameter.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InterestingTerms::None => "none",
+            InterestingTerms::List => "list",
+            InterestingTerms::Details => "details",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A term used to find similar documents
+pub struct InterestingTerm {
+    /// The term, prefixed by its field (e.g. `name:memory`).
+    pub term: String,
+    /// The boost of the term (`InterestingTerms::Details` only).
+    pub boost: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+/// A result of a MoreLikeThis request
+pub struct MoreLikeThisResult<T = Value> {
+    /// The similar documents.
+    pub response: QueryResponse<T>,
+    /// The source document (`MltMode::Handler` with a document source).
+    pub matched: Option<Value>,
+    /// The terms used to find similar documents (see `MoreLikeThis::interesting_terms`).
+    pub interesting_terms: Vec<InterestingTerm>,
+}
+
+#[derive(Debug)]
+/// The source of similar documents
+enum Source {
+    Id(String),
+    Text(String),
+}
+
+#[derive(Debug)]
+/// A MoreLikeThis request, which finds documents similar to a document or a text
+pub struct MoreLikeThis<'a, 'b> {
+    collection: &'a Collection<'b>,
+    mode: MltMode,
+    source: Option<Source>,
+    fields: Option<String>,
+    mlt_params: Vec<(String, String)>,
+    params: Vec<(String, String)>,
+    options: RequestOptions,
+}
+
+impl<'a, 'b> MoreLikeThis<'a, 'b> {
+    pub(crate) fn new(collection: &'a Collection<'b>) -> MoreLikeThis<'a, 'b> {
+        MoreLikeThis {
+            collection,
+            mode: MltMode::Handler,
+            source: None,
+            fields: None,
+            mlt_params: vec![],
+            params: vec![],
+            options: RequestOptions::default(),
+        }
+    }
+
+    fn set_mlt<T>(&mut self, param: &str, value: T) -> &mut Self
+        where T: std::string::ToString {
+        self.mlt_params.retain(|(p, _)| p != param);
+        self.mlt_params.push((param.to_string(), value.to_string()));
+        self
+    }
+
+    fn set<T>(&mut self, param: &str, value: T) -> &mut Self
+        where T: std::string::ToString {
+        self.params.retain(|(p, _)| p != param);
+        self.params.push((param.to_string(), value.to_string()));
+        self
+    }
+
+    /// Sets the transport of the request. Defaults to `MltMode::Handler`.
+    ///
+    /// # Arguments
+    /// * `mode` -
+    pub fn mode(&mut self, mode: MltMode) -> &mut Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Finds documents similar to an indexed document.
+    ///
+    /// # Arguments
+    /// * `id` - The unique key of the document.
+    pub fn like_id(&mut self, id: &str) -> &mut Self {
+        self.source = Some(Source::Id(id.to_string()));
+        self
+    }
+
+    /// Finds documents similar to a text (`MltMode::Handler` only). The text is sent as the body
+    /// of a POST request, so stream bodies need not be enabled in `solrconfig.xml`.
+    ///
+    /// # Arguments
+    /// * `text` -
+    pub fn like_text(&mut self, text: &str) -> &mut Self {
+        self.source = Some(Source::Text(text.to_string()));
+        self
+    }
+
+    /// Sets the fields used for similarity (comma separated), preferably with term vectors.
+    ///
+    /// # Arguments
+    /// * `fields` -
+    pub fn fields(&mut self, fields: &str) -> &mut Self {
+        self.fields = Some(fields.to_string());
+        self
+    }
+
+    /// Sets the minimum frequency of a term in the source. Solr defaults to 2.
+    ///
+    /// # Arguments
+    /// * `mintf` -
+    pub fn mintf(&mut self, mintf: u32) -> &mut Self {
+        self.set_mlt("mintf", mintf)
+    }
+
+    /// Sets the minimum number of documents containing a term. Solr defaults to 5.
+    ///
+    /// # Arguments
+    /// * `mindf` -
+    pub fn mindf(&mut self, mindf: u32) -> &mut Self {
+        self.set_mlt("mindf", mindf)
+    }
+
+    /// Sets the maximum number of documents containing a term, to ignore too common terms.
+    ///
+    /// # Arguments
+    /// * `maxdf` -
+    pub fn maxdf(&mut self, maxdf: u32) -> &mut Self {
+        self.set_mlt("maxdf", maxdf)
+    }
+
+    /// Sets the minimum length of a term.
+    ///
+    /// # Arguments
+    /// * `minwl` -
+    pub fn minwl(&mut self, minwl: u32) -> &mut Self {
+        self.set_mlt("minwl", minwl)
+    }
+
+    /// Sets the maximum number of terms of the generated query. Solr defaults to 25.
+    ///
+    /// # Arguments
+    /// * `maxqt` -
+    pub fn maxqt(&mut self, maxqt: u32) -> &mut Self {
+        self.set_mlt("maxqt", maxqt)
+    }
+
+    /// Sets whether the terms of the generated query are boosted by their relevance.
+    ///
+    /// # Arguments
+    /// * `boost` -
+    pub fn boost(&mut self, boost: bool) -> &mut Self {
+        self.set_mlt("boost", boost)
+    }
+
+    /// Sets the format of returned interesting terms (`MltMode::Handler` only).
+    ///
+    /// # Arguments
+    /// * `interesting_terms` -
+    pub fn interesting_terms(&mut self, interesting_terms: InterestingTerms) -> &mut Self {
+        self.set("mlt.interestingTerms", interesting_terms.as_str())
+    }
+
+    /// Sets the maximum number of returned documents.
+    ///
+    /// # Arguments
+    /// * `rows` -
+    pub fn rows(&mut self, rows: usize) -> &mut Self {
+        self.set("rows", rows)
+    }
+
+    /// Sets the offset of the first returned document.
+    ///
+    /// # Arguments
+    /// * `start` -
+    pub fn start(&mut self, start: usize) -> &mut Self {
+        self.set("start", start)
+    }
+
+    /// Limits document fields returned in the response.
+    ///
+    /// # Arguments
+    /// * `fl` -
+    pub fn fl(&mut self, fl: &str) -> &mut Self {
+        self.set("fl", fl)
+    }
+
+    /// Restricts the similar documents by a filter query.
+    ///
+    /// # Arguments
+    /// * `fq` -
+    pub fn fq(&mut self, fq: &str) -> &mut Self {
+        self.params.push(("fq".into(), fq.to_string()));
+        self
+    }
+
+    /// Overrides the client's authentication for the request.
+    ///
+    /// # Arguments
+    /// * `auth` -
+    pub fn auth(&mut self, auth: Auth) -> &mut Self {
+        self.options.auth(auth);
+        self
+    }
+
+    /// Sets a header sent with the request.
+    ///
+    /// # Arguments
+    /// * `name` - The header name.
+    /// * `value` - The header value.
+    pub fn header(&mut self, name: String, value: String) -> &mut Self {
+        self.options.header(name, value);
+        self
+    }
+
+    /// Returns the request path.
+    ///
+    /// # Arguments
+    /// * `unique_key` - The unique key field of the collection, used by the handler to find the
+    ///   source document.
+    pub(crate) fn build_path(&self, unique_key: &str) -> Result<String, SolrError> {
+        let mut params = vec![];
+        match (self.mode, &self.source) {
+            (_, None) => {
+                return Err(SolrError::InvalidInput("MoreLikeThis requires `like_id` or `like_text`".into()));
+            },
+            (MltMode::QueryParser, Some(Source::Text(_))) => {
+                return Err(SolrError::InvalidInput("the `{!mlt}` query parser does not support `like_text`".into()));
+            },
+            (MltMode::QueryParser, Some(Source::Id(id))) => {
+                let mut local_params = self.mlt_params.iter()
+                    .map(|(p, v)| format!("{}={}", p, local_param_value(v)))
+                    .collect::<Vec<_>>();
+                if let Some(fields) = &self.fields {
+                    local_params.insert(0, format!("qf={}", local_param_value(fields)));
+                }
+                params.push(("q".to_string(), format!("{{!mlt {}}}{}", local_params.join(" "), id)));
+            },
+            (MltMode::Handler, Some(source)) => {
+                match source {
+                    Source::Id(id) => params.push(("q".into(), format!("{{!term f={}}}{}", unique_key, id))),
+                    // The text is sent as the request body
+                    Source::Text(_) => {},
+                }
+                if let Some(fields) = &self.fields {
+                    params.push(("mlt.fl".into(), fields.clone()));
+                }
+                params.extend(self.mlt_params.iter().map(|(p, v)| (format!("mlt.{}", p), v.clone())));
+            },
+        }
+        params.extend(self.params.iter()
+            .filter(|(p, _)| self.mode == MltMode::Handler || p != "mlt.interestingTerms")
+            .cloned());
+
+        let handler = match self.mode {
+            MltMode::Handler => "mlt",
+            MltMode::QueryParser => "select",
+        };
+        let params = params.iter()
+            .map(|(p, v)| format!("{}={}", p, self.collection.client.url_encode(v)))
+            .collect::<Vec<_>>();
+        Ok(format!("{}/{}?{}", self.collection.name, handler, params.join("&")))
+    }
+
+    /// Commits the request and returns the similar documents.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use solrdrv::InterestingTerms;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+    /// # let mut products = solr.collections().get("products".into()).await?;
+    /// let related = products.more_like_this()
+    ///     .like_id("product-42")
+    ///     .fields("name,description")
+    ///     .mintf(1)
+    ///     .mindf(2)
+    ///     .interesting_terms(InterestingTerms::Details)
+    ///     .rows(5)
+    ///     .commit().await?;
+    /// for doc in &related.response.docs {
+    ///     println!("{}", doc["name"]);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/morelikethis.html
+    pub async fn commit(&self) -> Result<MoreLikeThisResult, SolrError> {
+        let unique_key = match (self.mode, &self.source) {
+            (MltMode::Handler, Some(Source::Id(_))) => self.collection.schema().unique_key_with(&self.options).await?,
+            _ => String::new(),
+        };
+        let path = self.build_path(&unique_key)?;
+        let url = self.collection.client.format_url(&path);
+        let mut res = match &self.source {
+            Some(Source::Text(text)) => self.collection.client.post_text(&path, text.clone(), &self.options).await?,
+            _ => self.collection.client.get_with(&path, &self.options).await?,
+        };
+
+        let matched = res.get_mut("match")
+            .and_then(|m| m.get_mut("docs"))
+            .and_then(|docs| docs.as_array_mut())
+            .and_then(|docs| docs.pop());
+        let interesting_terms = match res.get("interestingTerms") {
+            Some(terms) => parse_interesting_terms(terms).ok_or_else(|| SolrError::UnexpectedResponse {
+                url: url.clone(),
+                message: "malformed `interestingTerms`".into(),
+            })?,
+            None => vec![],
+        };
+        Ok(MoreLikeThisResult {
+            response: QueryResponse::parse(&url, res)?,
+            matched,
+            interesting_terms,
+        })
+    }
+
+    /// Same as `commit`, but deserializes the similar documents into type `T`.
+    pub async fn commit_as<T>(&self) -> Result<MoreLikeThisResult<T>, SolrError>
+        where T: DeserializeOwned {
+        let result = self.commit().await?;
+        Ok(MoreLikeThisResult {
+            response: result.response.into_typed()?,
+            matched: result.matched,
+            interesting_terms: result.interesting_terms,
+        })
+    }
+}
+
+/// Parses interesting terms returned as a list of terms or as a named list of boosts.
+pub(crate) fn parse_interesting_terms(terms: &Value) -> Option<Vec<InterestingTerm>> {
+    match terms.as_array() {
+        Some(list) if list.iter().all(Value::is_string) => {
+            list.iter().map(|t| Some(InterestingTerm { term: t.as_str()?.to_string(), boost: None })).collect()
+        },
+        _ => named_list(terms)?.into_iter()
+            .map(|(term, boost)| Some(InterestingTerm { term, boost: Some(boost.as_f64()?) }))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::Solr;
+
+    #[test]
+    fn more_like_this_path_and_terms() {
+        let solr = Solr::client("http".into(), "localhost".into(), 8983);
+        let products = Collection::new(&solr, "products".into());
+        let mut mlt = products.more_like_this();
+        mlt.like_id("p1").fields("name,body").mintf(1).interesting_terms(InterestingTerms::List);
+        assert_eq!(mlt.build_path("id").unwrap(),
+            "products/mlt?q=%7B%21term%20f%3Did%7Dp1&mlt.fl=name%2Cbody&mlt.mintf=1&mlt.interestingTerms=list");
+        mlt.mode(MltMode::QueryParser);
+        assert_eq!(mlt.build_path("id").unwrap(),
+            "products/select?q=%7B%21mlt%20qf%3Dname%2Cbody%20mintf%3D1%7Dp1");
+        mlt.like_text("some text");
+        assert!(mlt.build_path("id").is_err());
+        mlt.mode(MltMode::Handler);
+        assert_eq!(mlt.build_path("id").unwrap(),
+            "products/mlt?mlt.fl=name%2Cbody&mlt.mintf=1&mlt.interestingTerms=list");
+
+        let details = parse_interesting_terms(&json!(["name:memory", 1.0, "body:ram", 0.5])).unwrap();
+        assert_eq!(details[1], InterestingTerm { term: "body:ram".into(), boost: Some(0.5) });
+        let list = parse_interesting_terms(&json!(["name:memory", "body:ram"])).unwrap();
+        assert_eq!(list[1].boost, None);
+    }
+}
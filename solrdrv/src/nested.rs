@@ -0,0 +1,220 @@
+//! Helpers for nested (child) documents indexed as blocks.
+//!
+//! Child documents are either listed in the `_childDocuments_` field of the parent, or stored in
+//! labelled fields holding an object or an array of objects (Solr 8+, requires the `_nest_path_`
+//! field in the schema).
+//!
+//! # Example
+//! ```no_run
+//! use solrdrv::nested::{self, ChildTransformer};
+//! # use solrdrv::serde_json::json;
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+//! # let mut orders = solr.collections().get("orders".into()).await?;
+//! orders.add(json!({
+//!     "id": "order-1",
+//!     "type": "order",
+//!     "items": [
+//!         { "id": "order-1-1", "type": "item", "sku": "A-42" },
+//!         { "id": "order-1-2", "type": "item", "sku": "B-17" }
+//!     ]
+//! })).commit().await?;
+//!
+//! // Orders containing the item, each with its items
+//! let orders = orders.search()
+//!     .query(&nested::parent_query("type:order", "sku:A-42"))
+//!     .child_docs(&ChildTransformer::new().limit(10))
+//!     .commit().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # See
+//! https://lucene.apache.org/solr/guide/8_5/indexing-nested-documents.html
+
+use serde_json::Value;
+use crate::{local_param_value, SolrError, CHILD_DOCUMENTS_FIELD};
+
+/// Returns a block join query matching parents of children matching `query`.
+///
+/// # Arguments
+/// * `which` - A query matching all parent documents (e.g. `type:order`), never child documents.
+/// * `query` - A query matching child documents.
+///
+/// # See
+/// https://lucene.apache.org/solr/guide/8_5/other-parsers.html#block-join-parent-query-parser
+pub fn parent_query(which: &str, query: &str) -> String {
+    format!("{{!parent which={}}}{}", local_param_value(which), query)
+}
+
+/// Returns a block join query matching children of parents matching `query`.
+///
+/// # Arguments
+/// * `of` - A query matching all parent documents (e.g. `type:order`), never child documents.
+/// * `query` - A query matching parent documents.
+///
+/// # See
+/// https://lucene.apache.org/solr/guide/8_5/other-parsers.html#block-join-children-query-parser
+pub fn child_query(of: &str, query: &str) -> String {
+    format!("{{!child of={}}}{}", local_param_value(of), query)
+}
+
+/// Returns the child documents of a returned document, whether they were returned in
+/// `_childDocuments_` or in labelled fields.
+///
+/// # Arguments
+/// * `doc` - A returned document.
+pub fn child_documents(doc: &Value) -> Vec<&Value> {
+    let mut children = vec![];
+    if let Some(fields) = doc.as_object() {
+        for value in fields.values() {
+            match value {
+                Value::Object(_) => children.push(value),
+                Value::Array(values) => children.extend(values.iter().filter(|v| v.is_object())),
+                _ => {},
+            }
+        }
+    }
+    children
+}
+
+#[derive(Debug, Clone, Default)]
+/// A builder of the `[child]` document transformer, which returns child documents of each
+/// returned parent document
+pub struct ChildTransformer {
+    parent_filter: Option<String>,
+    child_filter: Option<String>,
+    limit: Option<i64>,
+    fl: Option<String>,
+}
+
+impl ChildTransformer {
+    /// Creates a new transformer. Without a parent filter, the schema must contain the
+    /// `_nest_path_` field.
+    pub fn new() -> ChildTransformer {
+        ChildTransformer::default()
+    }
+
+    /// Sets a query matching all parent documents. Required if the schema does not contain the
+    /// `_nest_path_` field.
+    ///
+    /// # Arguments
+    /// * `parent_filter` -
+    pub fn parent_filter(&mut self, parent_filter: &str) -> &mut Self {
+        self.parent_filter = Some(parent_filter.to_string());
+        self
+    }
+
+    /// Sets a query restricting which child documents are returned.
+    ///
+    /// # Arguments
+    /// * `child_filter` -
+    pub fn child_filter(&mut self, child_filter: &str) -> &mut Self {
+        self.child_filter = Some(child_filter.to_string());
+        self
+    }
+
+    /// Sets the maximum number of child documents returned per parent. Solr defaults to 10, use
+    /// `-1` for all children.
+    ///
+    /// # Arguments
+    /// * `limit` -
+    pub fn limit(&mut self, limit: i64) -> &mut Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Limits fields of the returned child documents.
+    ///
+    /// # Arguments
+    /// * `fl` -
+    pub fn fl(&mut self, fl: &str) -> &mut Self {
+        self.fl = Some(fl.to_string());
+        self
+    }
+
+    /// Builds the transformer to be used in the `fl` parameter.
+    ///
+    /// # Example
+    /// ```no_run
+    /// let child = solrdrv::ChildTransformer::new()
+    ///     .parent_filter("type:order")
+    ///     .limit(-1)
+    ///     .build();
+    /// // => [child parentFilter=type:order limit=-1]
+    /// ```
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/transforming-result-documents.html#child-childdoctransformerfactory
+    pub fn build(&self) -> String {
+        let mut params = vec!["[child".to_string()];
+        if let Some(parent_filter) = &self.parent_filter {
+            params.push(format!("parentFilter={}", local_param_value(parent_filter)));
+        }
+        if let Some(child_filter) = &self.child_filter {
+            params.push(format!("childFilter={}", local_param_value(child_filter)));
+        }
+        if let Some(limit) = self.limit {
+            params.push(format!("limit={}", limit));
+        }
+        if let Some(fl) = &self.fl {
+            params.push(format!("fl={}", local_param_value(fl)));
+        }
+        format!("{}]", params.join(" "))
+    }
+}
+
+/// Checks that a document is an object and that all its nested child documents are objects too.
+///
+/// # Arguments
+/// * `doc` - The document to check.
+pub(crate) fn validate_document(doc: &Value) -> Result<(), SolrError> {
+    let fields = match doc.as_object() {
+        Some(fields) => fields,
+        None => return Err(SolrError::InvalidInput(format!("expected a document object, got `{}`", doc))),
+    };
+    for (name, value) in fields {
+        match value {
+            Value::Array(values) if name == CHILD_DOCUMENTS_FIELD || values.iter().any(Value::is_object) => {
+                for child in values {
+                    if !child.is_object() {
+                        return Err(SolrError::InvalidInput(format!(
+                            "expected only child documents in field `{}`, got `{}`", name, child)));
+                    }
+                    validate_document(child)?;
+                }
+            },
+            _ if name == CHILD_DOCUMENTS_FIELD => {
+                return Err(SolrError::InvalidInput(format!(
+                    "expected an array of child documents in field `{}`, got `{}`", name, value)));
+            },
+            Value::Object(_) => validate_document(value)?,
+            _ => {},
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn nested_documents() {
+        assert!(validate_document(&json!({
+            "id": "1",
+            "items": [{ "id": "1-1" }, { "id": "1-2", "parts": { "id": "1-2-1" } }],
+            "_childDocuments_": [{ "id": "1-3" }]
+        })).is_ok());
+        assert!(validate_document(&json!({ "id": "1", "items": [{ "id": "1-1" }, 2] })).is_err());
+        assert!(validate_document(&json!({ "id": "1", "_childDocuments_": { "id": "1-1" } })).is_err());
+
+        assert_eq!(parent_query("type:order", "sku:A-42"), "{!parent which=type:order}sku:A-42");
+        assert_eq!(child_query("type:order AND shop:1", "id:1"),
+            "{!child of='type:order AND shop:1'}id:1");
+        assert_eq!(ChildTransformer::new().parent_filter("type:order").fl("id,sku").limit(-1).build(),
+            "[child parentFilter=type:order limit=-1 fl=id,sku]");
+    }
+}
@@ -0,0 +1,151 @@
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use crate::{Auth, Collection, QueryResponse, RequestOptions, SolrError};
+
+#[derive(Debug)]
+/// A real-time get request, which returns the latest versions of documents by their unique keys,
+/// including documents which were not committed yet
+pub struct RealTimeGet<'a, 'b> {
+    collection: &'a Collection<'b>,
+    ids: Vec<String>,
+    fl: Option<String>,
+    fq: Option<String>,
+    options: RequestOptions,
+}
+
+impl<'a, 'b> RealTimeGet<'a, 'b> {
+    pub(crate) fn new(collection: &'a Collection<'b>, ids: Vec<String>) -> RealTimeGet<'a, 'b> {
+        RealTimeGet {
+            collection,
+            ids,
+            fl: None,
+            fq: None,
+            options: RequestOptions::default(),
+        }
+    }
+
+    /// Limits document fields returned by the request.
+    ///
+    /// # Arguments
+    /// * `fl` -
+    pub fn fl(&mut self, fl: &str) -> &mut Self {
+        self.fl = Some(fl.to_string());
+        self
+    }
+
+    /// Returns the documents only if they match a filter query.
+    ///
+    /// # Arguments
+    /// * `fq` -
+    pub fn fq(&mut self, fq: &str) -> &mut Self {
+        self.fq = Some(fq.to_string());
+        self
+    }
+
+    /// Overrides the client's authentication for the request.
+    ///
+    /// # Arguments
+    /// * `auth` -
+    pub fn auth(&mut self, auth: Auth) -> &mut Self {
+        self.options.auth(auth);
+        self
+    }
+
+    /// Sets a header sent with the request.
+    ///
+    /// # Arguments
+    /// * `name` - The header name.
+    /// * `value` - The header value.
+    pub fn header(&mut self, name: String, value: String) -> &mut Self {
+        self.options.header(name, value);
+        self
+    }
+
+    pub(crate) fn build_path(&self) -> String {
+        let client = self.collection.client;
+        // Solr splits `ids` on commas, which can be escaped by a backslash
+        let ids = self.ids.iter()
+            .map(|id| id.replace('\\', "\\\\").replace(',', "\\,"))
+            .collect::<Vec<_>>()
+            .join(",");
+        let mut path = format!("{}/get?ids={}", self.collection.name, client.url_encode(&ids));
+        if let Some(fl) = &self.fl {
+            path.push_str(&format!("&fl={}", client.url_encode(fl)));
+        }
+        if let Some(fq) = &self.fq {
+            path.push_str(&format!("&fq={}", client.url_encode(fq)));
+        }
+        path
+    }
+
+    /// Commits the request and returns the found documents. Documents which do not exist (or do
+    /// not match the filter query) are omitted.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+    /// # let mut users = solr.collections().get("users".into()).await?;
+    /// let found = users.get_by_ids(&["1", "2"])
+    ///     .fl("id,name")
+    ///     .commit().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/realtime-get.html
+    pub async fn commit(&self) -> Result<Vec<Value>, SolrError> {
+        if self.ids.is_empty() {
+            return Ok(vec![]);
+        }
+        let path = self.build_path();
+        let url = self.collection.client.format_url(&path);
+        let res = self.collection.client.get_with(&path, &self.options).await?;
+        Ok(QueryResponse::parse(&url, res)?.docs)
+    }
+
+    /// Same as `commit`, but deserializes the found documents into type `T`.
+    pub async fn commit_as<T>(&self) -> Result<Vec<T>, SolrError>
+        where T: DeserializeOwned {
+        self.commit().await?
+            .into_iter()
+            .map(|doc| serde_json::from_value(doc).map_err(SolrError::from))
+            .collect()
+    }
+
+    /// Commits the request and returns the first found document deserialized into type `T`, or
+    /// `None` if no document was found.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # #[derive(serde::Deserialize)]
+    /// # struct User { name: String }
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+    /// # let mut users = solr.collections().get("users".into()).await?;
+    /// let user: Option<User> = users.get_by_id("1").first_as().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn first_as<T>(&self) -> Result<Option<T>, SolrError>
+        where T: DeserializeOwned {
+        match self.commit().await?.into_iter().next() {
+            Some(doc) => Ok(Some(serde_json::from_value(doc)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Solr;
+
+    #[test]
+    fn real_time_get_path() {
+        let solr = Solr::client("http".into(), "localhost".into(), 8983);
+        let users = Collection::new(&solr, "users".into());
+        assert_eq!(users.get_by_ids(&["1", "a,b"]).fl("id").build_path(), "users/get?ids=1%2Ca%5C%2Cb&fl=id");
+    }
+}
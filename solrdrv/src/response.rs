@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use crate::group::{DocList, GroupResult};
+use crate::highlight::{Highlighted, Snippets};
+use crate::json_facet::Bucket;
+use crate::spellcheck::SpellcheckResult;
+use crate::stats::FieldStats;
+use crate::{FacetCounts, SolrError};
+
+#[derive(Debug, Clone, Deserialize)]
+/// A header of a Solr response
+pub struct ResponseHeader {
+    /// The status of the request (`0` on success).
+    #[serde(default)]
+    pub status: i64,
+    /// The time (in milliseconds) it took Solr to process the request.
+    #[serde(rename = "QTime", default)]
+    pub q_time: u64,
+    /// Set if the results are partial (e.g. because `timeAllowed` was exceeded).
+    #[serde(rename = "partialResults")]
+    pub partial_results: Option<bool>,
+    /// The request parameters echoed by Solr (see `Query::echo_params`).
+    #[serde(default)]
+    pub params: HashMap<String, Value>,
+}
+
+#[derive(Debug, Clone)]
+/// A full response of a query, with documents of type `T`
+pub struct QueryResponse<T = Value> {
+    /// The response header, unless omitted using `Query::omit_header`.
+    pub header: Option<ResponseHeader>,
+    /// The number of documents matching the query. Zero for grouped results not returned in the
+    /// main result (see `grouped`).
+    pub num_found: u64,
+    /// The offset of the first returned document.
+    pub start: u64,
+    /// The maximum score of matching documents, if scores were requested.
+    pub max_score: Option<f64>,
+    /// Whether `num_found` is exact (Solr 8.6+, see `minExactCount`).
+    pub num_found_exact: Option<bool>,
+    /// The returned documents.
+    pub docs: Vec<T>,
+    /// The cursor mark of the next page, if a cursor was used.
+    pub next_cursor_mark: Option<String>,
+    /// The parsed `grouped` section, keyed by the grouping field, function or query.
+    pub grouped: Option<HashMap<String, GroupResult>>,
+    /// The parsed `expanded` section, keyed by values of the collapse field.
+    pub expanded: Option<HashMap<String, DocList>>,
+    /// The parsed `facet_counts` section.
+    pub facet_counts: Option<FacetCounts>,
+    /// The parsed `facets` section (JSON Facet API), i.e. the root bucket.
+    pub facets: Option<Bucket>,
+    /// The parsed `highlighting` section, keyed by unique keys of the documents.
+    pub highlighting: Option<HashMap<String, Snippets>>,
+    /// The `debug` section.
+    pub debug: Option<Value>,
+    /// The parsed `spellcheck` section.
+    pub spellcheck: Option<SpellcheckResult>,
+    /// The parsed `stats` section, keyed by field names (or `StatsField::key`).
+    pub stats: Option<HashMap<String, FieldStats>>,
+}
+
+impl QueryResponse {
+    /// Parses a response of the `/select` handler.
+    ///
+    /// # Arguments
+    /// * `url` - The URL of the request, used in errors.
+    /// * `res` - The response JSON.
+    pub(crate) fn parse(url: &str, mut res: Value) -> Result<QueryResponse, SolrError> {
+        let unexpected = |message: &str| SolrError::UnexpectedResponse {
+            url: url.to_string(),
+            message: message.to_string(),
+        };
+
+        let header = match res.get_mut("responseHeader").map(Value::take) {
+            Some(h) => match serde_json::from_value(h) {
+                Ok(h) => Some(h),
+                Err(source) => return Err(SolrError::Json { url: Some(url.to_string()), source }),
+            },
+            None => None,
+        };
+
+        let grouped = match res.get_mut("grouped").map(Value::take) {
+            Some(grouped) => match serde_json::from_value(grouped) {
+                Ok(grouped) => Some(grouped),
+                Err(source) => return Err(SolrError::Json { url: Some(url.to_string()), source }),
+            },
+            None => None,
+        };
+
+        // Grouped results are returned instead of the main result
+        let mut response = match res.get_mut("response").map(Value::take) {
+            Some(r) if r.is_object() => r,
+            None if grouped.is_some() => json!({ "numFound": 0, "docs": [] }),
+            _ => return Err(unexpected("missing `response`")),
+        };
+        let docs = match response["docs"].take() {
+            Value::Array(docs) => docs,
+            _ => return Err(unexpected("missing `response.docs`")),
+        };
+        let num_found = match response["numFound"].as_u64() {
+            Some(n) => n,
+            None => return Err(unexpected("missing `response.numFound`")),
+        };
+
+        let mut section = |name: &str| res.get_mut(name).map(Value::take);
+        Ok(QueryResponse {
+            header,
+            num_found,
+            start: response["start"].as_u64().unwrap_or(0),
+            max_score: response["maxScore"].as_f64(),
+            num_found_exact: response["numFoundExact"].as_bool(),
+            docs,
+            next_cursor_mark: section("nextCursorMark").and_then(|m| m.as_str().map(String::from)),
+            grouped,
+            expanded: match section("expanded") {
+                Some(expanded) => match serde_json::from_value(expanded) {
+                    Ok(expanded) => Some(expanded),
+                    Err(source) => return Err(SolrError::Json { url: Some(url.to_string()), source }),
+                },
+                None => None,
+            },
+            facet_counts: match section("facet_counts") {
+                Some(facet_counts) => Some(FacetCounts::parse(url, &facet_counts)?),
+                None => None,
+            },
+            facets: match section("facets") {
+                Some(facets) => Some(Bucket::parse(url, &facets)?),
+                None => None,
+            },
+            highlighting: match section("highlighting") {
+                Some(highlighting) => match serde_json::from_value(highlighting) {
+                    Ok(highlighting) => Some(highlighting),
+                    Err(source) => return Err(SolrError::Json { url: Some(url.to_string()), source }),
+                },
+                None => None,
+            },
+            debug: section("debug"),
+            spellcheck: match section("spellcheck") {
+                Some(spellcheck) => Some(SpellcheckResult::parse(url, &spellcheck)?),
+                None => None,
+            },
+            stats: match section("stats") {
+                Some(stats) => Some(FieldStats::parse_section(url, &stats)?),
+                None => None,
+            },
+        })
+    }
+
+    /// Pairs the returned documents with their highlighted snippets.
+    ///
+    /// # Arguments
+    /// * `unique_key` - The unique key field of the collection, which must be returned in `fl`.
+    pub fn highlighted(self, unique_key: &str) -> Vec<Highlighted> {
+        let mut highlighting = self.highlighting.unwrap_or_default();
+        self.docs.into_iter()
+            .map(|doc| {
+                let snippets = match &doc[unique_key] {
+                    Value::Null => None,
+                    Value::String(id) => highlighting.remove(id),
+                    id => highlighting.remove(&id.to_string()),
+                };
+                Highlighted { doc, snippets: snippets.unwrap_or_default() }
+            })
+            .collect()
+    }
+
+    /// Deserializes the returned documents into type `T`.
+    pub fn into_typed<T>(self) -> Result<QueryResponse<T>, SolrError>
+        where T: DeserializeOwned {
+        let docs = self.docs.into_iter()
+            .map(serde_json::from_value)
+            .collect::<Result<Vec<T>, _>>()?;
+        Ok(QueryResponse {
+            header: self.header,
+            num_found: self.num_found,
+            start: self.start,
+            max_score: self.max_score,
+            num_found_exact: self.num_found_exact,
+            docs,
+            next_cursor_mark: self.next_cursor_mark,
+            grouped: self.grouped,
+            expanded: self.expanded,
+            facet_counts: self.facet_counts,
+            facets: self.facets,
+            highlighting: self.highlighting,
+            debug: self.debug,
+            spellcheck: self.spellcheck,
+            stats: self.stats,
+        })
+    }
+}
+
+impl<T> QueryResponse<T> {
+    /// Returns the best collation of the spellcheck ("did you mean"), if any.
+    pub fn did_you_mean(&self) -> Option<&str> {
+        self.spellcheck.as_ref()
+            .and_then(|s| s.collations.first())
+            .map(|c| c.query.as_str())
+    }
+
+    /// Returns the time (in milliseconds) it took Solr to process the query, if the header was not
+    /// omitted.
+    pub fn q_time(&self) -> Option<u64> {
+        self.header.as_ref().map(|h| h.q_time)
+    }
+}
+
+/// Returns entries of a named list in any of the `json.nl` formats (`flat`, `map`, `arrarr`). A
+/// `null` name is returned as an empty string.
+pub(crate) fn named_list(list: &Value) -> Option<Vec<(String, &Value)>> {
+    let name = |name: &Value| match name {
+        Value::Null => Some(String::new()),
+        Value::String(name) => Some(name.clone()),
+        _ => None,
+    };
+    match list {
+        Value::Object(entries) => Some(entries.iter().map(|(k, v)| (k.clone(), v)).collect()),
+        Value::Array(items) if items.iter().all(|item| matches!(item.as_array(), Some(pair) if pair.len() == 2)) => {
+            items.iter().map(|pair| Some((name(&pair[0])?, &pair[1]))).collect()
+        },
+        Value::Array(items) if items.len() % 2 == 0 => {
+            items.chunks(2).map(|pair| Some((name(&pair[0])?, &pair[1]))).collect()
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_response_parse() {
+        let res = json!({
+            "responseHeader": { "status": 0, "QTime": 3, "params": { "q": "*:*" } },
+            "response": { "numFound": 42, "start": 10, "maxScore": 1.5, "docs": [{ "id": "1" }] },
+            "nextCursorMark": "AoE",
+            "highlighting": { "1": {} }
+        });
+        let response = QueryResponse::parse("http://localhost", res).unwrap();
+        assert_eq!(response.num_found, 42);
+        assert_eq!(response.start, 10);
+        assert_eq!(response.max_score, Some(1.5));
+        assert_eq!(response.q_time(), Some(3));
+        assert_eq!(response.docs, vec![json!({ "id": "1" })]);
+        assert_eq!(response.next_cursor_mark.as_deref(), Some("AoE"));
+        assert!(response.highlighting.is_some());
+        assert!(response.facet_counts.is_none());
+
+        let res = json!({
+            "response": { "numFound": 2, "docs": [{ "id": "1" }, { "id": "2" }] },
+            "highlighting": { "1": { "body": ["<em>Solr</em> rocks"] }, "2": {} }
+        });
+        let highlighted = QueryResponse::parse("http://localhost", res).unwrap().highlighted("id");
+        assert_eq!(highlighted[0].snippets["body"], vec!["<em>Solr</em> rocks".to_string()]);
+        assert!(highlighted[1].snippets.is_empty());
+
+
+        let err = QueryResponse::parse("http://localhost", json!({})).unwrap_err();
+        assert!(matches!(err, SolrError::UnexpectedResponse { .. }));
+    }
+}
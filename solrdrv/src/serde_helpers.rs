@@ -0,0 +1,139 @@
+//! Serde helpers for mapping Solr documents onto your own structures.
+//!
+//! # Example
+//! ```no_run
+//! use chrono::{DateTime, Utc};
+//! use serde::{Serialize, Deserialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct User {
+//!     id: String,
+//!     // A multi-valued field
+//!     #[serde(default, deserialize_with = "solrdrv::serde_helpers::one_or_many")]
+//!     tags: Vec<String>,
+//!     // A `pdate` field
+//!     #[serde(with = "solrdrv::serde_helpers::solr_date")]
+//!     birthday: DateTime<Utc>,
+//! }
+//! ```
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::de::{self, Deserialize, DeserializeOwned, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use serde_json::Value;
+
+/// Formats a date using the format expected by Solr date fields (e.g. `2020-05-26T10:00:00.000Z`).
+///
+/// # Arguments
+/// * `date` -
+pub fn format_date(date: &DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Parses a date returned by Solr (e.g. `2020-05-26T10:00:00Z`).
+///
+/// # Arguments
+/// * `string` -
+pub fn parse_date(string: &str) -> Result<DateTime<Utc>, chrono::ParseError> {
+    DateTime::parse_from_rfc3339(string).map(|d| d.with_timezone(&Utc))
+}
+
+/// (De)serializes a `DateTime<Utc>` as a Solr date string. Use with `#[serde(with = "...")]`.
+pub mod solr_date {
+    use super::*;
+
+    /// Serializes a date as a Solr date string.
+    pub fn serialize<S>(date: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer {
+        serializer.serialize_str(&format_date(date))
+    }
+
+    /// Deserializes a Solr date string.
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+        where D: Deserializer<'de> {
+        let string = String::deserialize(deserializer)?;
+        parse_date(&string).map_err(de::Error::custom)
+    }
+
+    /// (De)serializes an `Option<DateTime<Utc>>` as an optional Solr date string.
+    pub mod option {
+        use super::*;
+
+        /// Serializes an optional date as a Solr date string.
+        pub fn serialize<S>(date: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error>
+            where S: Serializer {
+            match date {
+                Some(date) => serializer.serialize_some(&format_date(date)),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        /// Deserializes an optional Solr date string.
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+            where D: Deserializer<'de> {
+            match Option::<String>::deserialize(deserializer)? {
+                Some(string) => parse_date(&string).map(Some).map_err(de::Error::custom),
+                None => Ok(None),
+            }
+        }
+    }
+
+    /// (De)serializes a `Vec<DateTime<Utc>>` as a multi-valued Solr date field. A single date is
+    /// deserialized as a vector with one item.
+    pub mod many {
+        use super::*;
+
+        /// Serializes dates as an array of Solr date strings.
+        pub fn serialize<S>(dates: &[DateTime<Utc>], serializer: S) -> Result<S::Ok, S::Error>
+            where S: Serializer {
+            dates.iter().map(format_date).collect::<Vec<_>>().serialize(serializer)
+        }
+
+        /// Deserializes a Solr date string or an array of Solr date strings.
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<DateTime<Utc>>, D::Error>
+            where D: Deserializer<'de> {
+            one_or_many::<D, String>(deserializer)?
+                .iter()
+                .map(|string| parse_date(string).map_err(de::Error::custom))
+                .collect()
+        }
+    }
+}
+
+/// Deserializes a field which can be either a single value or an array of values into a vector,
+/// e.g. a field which changed from single-valued to multi-valued. Use with
+/// `#[serde(default, deserialize_with = "...")]`.
+pub fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+    where D: Deserializer<'de>, T: DeserializeOwned {
+    match Value::deserialize(deserializer)? {
+        Value::Array(values) => values.into_iter()
+            .map(|v| serde_json::from_value(v).map_err(de::Error::custom))
+            .collect(),
+        Value::Null => Ok(vec![]),
+        value => serde_json::from_value(value).map(|v| vec![v]).map_err(de::Error::custom),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serde_helpers_dates_and_multi_values() {
+        #[derive(serde::Serialize, serde::Deserialize)]
+        struct Doc {
+            #[serde(default, deserialize_with = "one_or_many")]
+            tags: Vec<String>,
+            #[serde(with = "solr_date")]
+            created: chrono::DateTime<chrono::Utc>,
+        }
+
+        let doc: Doc = serde_json::from_value(json!({
+            "tags": "single",
+            "created": "2020-05-26T10:00:00Z"
+        })).unwrap();
+        assert_eq!(doc.tags, vec!["single".to_string()]);
+        assert_eq!(serde_json::to_value(&doc).unwrap()["created"], "2020-05-26T10:00:00.000Z");
+    }
+}
@@ -0,0 +1,260 @@
+use serde_json::Value;
+use crate::response::named_list;
+use crate::SolrError;
+
+#[derive(Debug, Clone, Default)]
+/// A builder of spellcheck parameters
+pub struct Spellcheck {
+    params: Vec<(String, String)>,
+}
+
+impl Spellcheck {
+    /// Creates new spellcheck parameters.
+    pub fn new() -> Spellcheck {
+        Spellcheck::default()
+    }
+
+    /// Sets a spellcheck parameter (without the `spellcheck.` prefix).
+    ///
+    /// # Arguments
+    /// * `param` - The parameter name, e.g. `accuracy`.
+    /// * `value` - The parameter value.
+    pub fn set<T>(&mut self, param: &str, value: T) -> &mut Self
+        where T: std::string::ToString {
+        let param = format!("spellcheck.{}", param);
+        self.params.retain(|(p, _)| *p != param);
+        self.params.push((param, value.to_string()));
+        self
+    }
+
+    /// Sets the query to spellcheck. Defaults to `q`.
+    ///
+    /// # Arguments
+    /// * `query` -
+    pub fn query(&mut self, query: &str) -> &mut Self {
+        self.set("q", query)
+    }
+
+    /// Adds a dictionary to use, as configured in `solrconfig.xml`. Can be set multiple times.
+    ///
+    /// # Arguments
+    /// * `dictionary` -
+    pub fn dictionary(&mut self, dictionary: &str) -> &mut Self {
+        self.params.push(("spellcheck.dictionary".into(), dictionary.to_string()));
+        self
+    }
+
+    /// Sets the maximum number of suggestions per misspelled term. Solr defaults to 1.
+    ///
+    /// # Arguments
+    /// * `count` -
+    pub fn count(&mut self, count: usize) -> &mut Self {
+        self.set("count", count)
+    }
+
+    /// Sets whether to return collations, i.e. the whole query rewritten using the suggestions.
+    ///
+    /// # Arguments
+    /// * `collate` -
+    pub fn collate(&mut self, collate: bool) -> &mut Self {
+        self.set("collate", collate)
+    }
+
+    /// Sets the maximum number of returned collations. Solr defaults to 1.
+    ///
+    /// # Arguments
+    /// * `max_collations` -
+    pub fn max_collations(&mut self, max_collations: usize) -> &mut Self {
+        self.set("maxCollations", max_collations)
+    }
+
+    /// Sets how many collations are tested against the index, so that only collations with hits
+    /// are returned. Solr defaults to 0 (no testing).
+    ///
+    /// # Arguments
+    /// * `max_collation_tries` -
+    pub fn max_collation_tries(&mut self, max_collation_tries: usize) -> &mut Self {
+        self.set("maxCollationTries", max_collation_tries)
+    }
+
+    /// Sets whether to return hit counts of collations and the corrections they consist of.
+    ///
+    /// # Arguments
+    /// * `collate_extended_results` -
+    pub fn collate_extended_results(&mut self, collate_extended_results: bool) -> &mut Self {
+        self.set("collateExtendedResults", collate_extended_results)
+    }
+
+    /// Sets whether to return frequencies of suggestions and the original terms.
+    ///
+    /// # Arguments
+    /// * `extended_results` -
+    pub fn extended_results(&mut self, extended_results: bool) -> &mut Self {
+        self.set("extendedResults", extended_results)
+    }
+
+    /// Sets whether to return only suggestions more frequent than the original term.
+    ///
+    /// # Arguments
+    /// * `only_more_popular` -
+    pub fn only_more_popular(&mut self, only_more_popular: bool) -> &mut Self {
+        self.set("onlyMorePopular", only_more_popular)
+    }
+
+    pub(crate) fn to_params(&self) -> Vec<(String, String)> {
+        self.params.clone()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A suggested correction of a term
+pub struct Alternative {
+    /// The suggested word.
+    pub word: String,
+    /// The frequency of the word (see `Spellcheck::extended_results`).
+    pub freq: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Suggestions for a misspelled term
+pub struct Suggestion {
+    /// The misspelled term.
+    pub original: String,
+    /// The number of suggestions.
+    pub num_found: u64,
+    /// The offset of the term in the query.
+    pub start_offset: u64,
+    /// The offset of the end of the term in the query.
+    pub end_offset: u64,
+    /// The frequency of the misspelled term (see `Spellcheck::extended_results`).
+    pub orig_freq: Option<u64>,
+    /// The suggested corrections.
+    pub alternatives: Vec<Alternative>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// The query rewritten using suggestions
+pub struct Collation {
+    /// The rewritten query.
+    pub query: String,
+    /// The number of documents matching the query (see `Spellcheck::collate_extended_results`).
+    pub hits: Option<u64>,
+    /// The misspelled terms and their corrections.
+    pub corrections: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// Parsed `spellcheck` section of a query response
+pub struct SpellcheckResult {
+    /// Whether the query is spelled correctly (see `Spellcheck::extended_results`).
+    pub correctly_spelled: Option<bool>,
+    /// The suggestions for misspelled terms.
+    pub suggestions: Vec<Suggestion>,
+    /// The collations, best first.
+    pub collations: Vec<Collation>,
+}
+
+impl SpellcheckResult {
+    /// Parses the `spellcheck` section.
+    ///
+    /// # Arguments
+    /// * `url` - The URL of the request, used in errors.
+    /// * `section` - The section JSON.
+    pub(crate) fn parse(url: &str, section: &Value) -> Result<SpellcheckResult, SolrError> {
+        let unexpected = |name: &str| SolrError::UnexpectedResponse {
+            url: url.to_string(),
+            message: format!("malformed `spellcheck.{}`", name),
+        };
+
+        let mut result = SpellcheckResult {
+            correctly_spelled: section["correctlySpelled"].as_bool(),
+            ..SpellcheckResult::default()
+        };
+        if !section["suggestions"].is_null() {
+            let suggestions = named_list(&section["suggestions"]).ok_or_else(|| unexpected("suggestions"))?;
+            for (original, suggestion) in suggestions {
+                let alternatives = suggestion["suggestion"].as_array()
+                    .ok_or_else(|| unexpected("suggestions"))?
+                    .iter()
+                    .map(|alternative| match alternative {
+                        Value::String(word) => Some(Alternative { word: word.clone(), freq: None }),
+                        _ => Some(Alternative {
+                            word: alternative["word"].as_str()?.to_string(),
+                            freq: alternative["freq"].as_u64(),
+                        }),
+                    })
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| unexpected("suggestions"))?;
+                result.suggestions.push(Suggestion {
+                    original,
+                    num_found: suggestion["numFound"].as_u64().unwrap_or(alternatives.len() as u64),
+                    start_offset: suggestion["startOffset"].as_u64().unwrap_or(0),
+                    end_offset: suggestion["endOffset"].as_u64().unwrap_or(0),
+                    orig_freq: suggestion["origFreq"].as_u64(),
+                    alternatives,
+                });
+            }
+        }
+        if !section["collations"].is_null() {
+            let collations = named_list(&section["collations"]).ok_or_else(|| unexpected("collations"))?;
+            for (_, collation) in collations {
+                let collation = match collation {
+                    Value::String(query) => Collation { query: query.clone(), hits: None, corrections: vec![] },
+                    _ => Collation {
+                        query: collation["collationQuery"].as_str()
+                            .ok_or_else(|| unexpected("collations"))?
+                            .to_string(),
+                        hits: collation["hits"].as_u64(),
+                        corrections: named_list(&collation["misspellingsAndCorrections"])
+                            .unwrap_or_default()
+                            .into_iter()
+                            .filter_map(|(original, correction)| Some((original, correction.as_str()?.to_string())))
+                            .collect(),
+                    },
+                };
+                result.collations.push(collation);
+            }
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::QueryResponse;
+
+    #[test]
+    fn spellcheck_response() {
+        let res = json!({
+            "response": { "numFound": 0, "docs": [] },
+            "spellcheck": {
+                "suggestions": ["delll", {
+                    "numFound": 1, "startOffset": 5, "endOffset": 10, "origFreq": 0,
+                    "suggestion": [{ "word": "dell", "freq": 7 }]
+                }],
+                "correctlySpelled": false,
+                "collations": ["collation", {
+                    "collationQuery": "name:dell", "hits": 7,
+                    "misspellingsAndCorrections": ["delll", "dell"]
+                }]
+            }
+        });
+        let response = QueryResponse::parse("http://localhost", res).unwrap();
+        assert_eq!(response.did_you_mean(), Some("name:dell"));
+        let spellcheck = response.spellcheck.unwrap();
+        assert_eq!(spellcheck.correctly_spelled, Some(false));
+        assert_eq!(spellcheck.suggestions[0].original, "delll");
+        assert_eq!(spellcheck.suggestions[0].alternatives[0], Alternative { word: "dell".into(), freq: Some(7) });
+        assert_eq!(spellcheck.collations[0].hits, Some(7));
+        assert_eq!(spellcheck.collations[0].corrections, vec![("delll".to_string(), "dell".to_string())]);
+
+        let simple = SpellcheckResult::parse("http://localhost", &json!({
+            "suggestions": [["delll", { "numFound": 1, "suggestion": ["dell"] }]],
+            "collations": [["collation", "name:dell"]]
+        })).unwrap();
+        assert_eq!(simple.suggestions[0].alternatives[0].word, "dell");
+        assert_eq!(simple.collations[0].query, "name:dell");
+    }
+}
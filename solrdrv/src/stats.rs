@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+use serde_json::Value;
+use crate::response::named_list;
+use crate::{local_param_value, SolrError};
+
+#[derive(Debug, Clone, Default)]
+/// A builder of stats component parameters
+pub struct Stats {
+    params: Vec<(String, String)>,
+}
+
+impl Stats {
+    /// Creates new stats parameters.
+    pub fn new() -> Stats {
+        Stats::default()
+    }
+
+    /// Adds statistics of a field. Can be set multiple times.
+    ///
+    /// # Arguments
+    /// * `field` -
+    pub fn field(&mut self, field: &StatsField) -> &mut Self {
+        self.params.push(("stats.field".into(), field.build()));
+        self
+    }
+
+    /// Computes the statistics also per value of a field, returned in `FieldStats::facets`.
+    /// Can be set multiple times. Deprecated in Solr in favor of pivot facets with stats.
+    ///
+    /// # Arguments
+    /// * `field` -
+    pub fn facet(&mut self, field: &str) -> &mut Self {
+        self.params.push(("stats.facet".into(), field.to_string()));
+        self
+    }
+
+    pub(crate) fn to_params(&self) -> Vec<(String, String)> {
+        self.params.clone()
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A builder of a `stats.field` parameter, computing statistics of a field or function over the
+/// matching documents
+pub struct StatsField {
+    field: String,
+    local_params: Vec<(String, String)>,
+}
+
+impl StatsField {
+    /// Creates new statistics of a field. By default, Solr computes `min`, `max`, `count`,
+    /// `missing`, `sum`, `sumOfSquares`, `mean` and `stddev`.
+    ///
+    /// # Arguments
+    /// * `field` - The field name or a function query.
+    pub fn new(field: &str) -> StatsField {
+        StatsField {
+            field: field.to_string(),
+            local_params: vec![],
+        }
+    }
+
+    /// Sets a local param.
+    ///
+    /// # Arguments
+    /// * `param` - The local param name.
+    /// * `value` - The local param value.
+    pub fn set<T>(&mut self, param: &str, value: T) -> &mut Self
+        where T: std::string::ToString {
+        self.local_params.retain(|(p, _)| p != param);
+        self.local_params.push((param.to_string(), value.to_string()));
+        self
+    }
+
+    /// Computes only the selected statistic(s), e.g. `min` or `countDistinct`. Can be set multiple
+    /// times.
+    ///
+    /// # Arguments
+    /// * `stat` -
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/the-stats-component.html#statistics-supported
+    pub fn stat(&mut self, stat: &str) -> &mut Self {
+        self.set(stat, true)
+    }
+
+    /// Computes percentiles, e.g. `&[50.0, 90.0, 99.0]`.
+    ///
+    /// # Arguments
+    /// * `percentiles` -
+    pub fn percentiles(&mut self, percentiles: &[f64]) -> &mut Self {
+        let percentiles = percentiles.iter().map(f64::to_string).collect::<Vec<_>>();
+        self.set("percentiles", percentiles.join(","))
+    }
+
+    /// Computes the estimated number of distinct values (HyperLogLog).
+    ///
+    /// # Arguments
+    /// * `accuracy` - A trade-off between memory and accuracy between `0.0` and `1.0`, Solr
+    ///   defaults to `0.3`.
+    pub fn cardinality(&mut self, accuracy: f64) -> &mut Self {
+        self.set("cardinality", accuracy)
+    }
+
+    /// Sets the name under which the statistics are returned (defaults to the field name).
+    ///
+    /// # Arguments
+    /// * `key` -
+    pub fn key(&mut self, key: &str) -> &mut Self {
+        self.set("key", key)
+    }
+
+    /// Tags the statistics, e.g. to be referenced by a pivot facet (`{!stats=tag}`).
+    ///
+    /// # Arguments
+    /// * `tag` -
+    pub fn tag(&mut self, tag: &str) -> &mut Self {
+        self.set("tag", tag)
+    }
+
+    /// Excludes filter queries tagged with `tag` when computing the statistics.
+    ///
+    /// # Arguments
+    /// * `tag` -
+    pub fn exclude(&mut self, tag: &str) -> &mut Self {
+        let tags = match self.local_params.iter().find(|(p, _)| p == "ex") {
+            Some((_, tags)) => format!("{},{}", tags, tag),
+            None => tag.to_string(),
+        };
+        self.set("ex", tags)
+    }
+
+    /// Builds the `stats.field` parameter value.
+    ///
+    /// # Example
+    /// ```no_run
+    /// let stats = solrdrv::StatsField::new("price").percentiles(&[50.0, 99.0]).exclude("brand").build();
+    /// // => {!percentiles='50,99' ex=brand}price
+    /// ```
+    pub fn build(&self) -> String {
+        if self.local_params.is_empty() {
+            return self.field.clone();
+        }
+        let params = self.local_params.iter()
+            .map(|(p, v)| format!("{}={}", p, local_param_value(v)))
+            .collect::<Vec<_>>();
+        format!("{{!{}}}{}", params.join(" "), self.field)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// Statistics of a single field
+pub struct FieldStats {
+    /// The minimum value (a number, or a string for dates and strings).
+    pub min: Option<Value>,
+    /// The maximum value.
+    pub max: Option<Value>,
+    /// The number of values.
+    pub count: Option<u64>,
+    /// The number of documents without a value.
+    pub missing: Option<u64>,
+    /// The sum of values.
+    pub sum: Option<f64>,
+    /// The sum of squares of values.
+    pub sum_of_squares: Option<f64>,
+    /// The mean value (a number, or a string for dates).
+    pub mean: Option<Value>,
+    /// The standard deviation.
+    pub stddev: Option<f64>,
+    /// The requested percentiles as `(percentile, value)` pairs.
+    pub percentiles: Vec<(f64, f64)>,
+    /// The estimated number of distinct values.
+    pub cardinality: Option<u64>,
+    /// The exact number of distinct values (`countDistinct`).
+    pub count_distinct: Option<u64>,
+    /// The distinct values (`distinctValues`).
+    pub distinct_values: Option<Vec<Value>>,
+    /// The statistics per value of a `stats.facet` field, keyed by the facet field and value.
+    pub facets: HashMap<String, HashMap<String, FieldStats>>,
+}
+
+impl FieldStats {
+    /// Parses statistics of a single field.
+    ///
+    /// # Arguments
+    /// * `url` - The URL of the request, used in errors.
+    /// * `stats` - The statistics JSON.
+    pub(crate) fn parse(url: &str, stats: &Value) -> Result<FieldStats, SolrError> {
+        let unexpected = |name: &str| SolrError::UnexpectedResponse {
+            url: url.to_string(),
+            message: format!("malformed `stats.stats_fields.{}`", name),
+        };
+        let present = |name: &str| match &stats[name] {
+            Value::Null => None,
+            value => Some(value.clone()),
+        };
+
+        let percentiles = match &stats["percentiles"] {
+            Value::Null => vec![],
+            list => named_list(list)
+                .and_then(|list| list.into_iter()
+                    .map(|(p, v)| Some((p.parse().ok()?, v.as_f64()?)))
+                    .collect())
+                .ok_or_else(|| unexpected("percentiles"))?,
+        };
+        let mut facets = HashMap::new();
+        if let Some(fields) = stats["facets"].as_object() {
+            for (field, values) in fields {
+                let mut field_facets = HashMap::new();
+                for (value, value_stats) in values.as_object().ok_or_else(|| unexpected("facets"))? {
+                    field_facets.insert(value.clone(), FieldStats::parse(url, value_stats)?);
+                }
+                facets.insert(field.clone(), field_facets);
+            }
+        }
+        Ok(FieldStats {
+            min: present("min"),
+            max: present("max"),
+            count: stats["count"].as_u64(),
+            missing: stats["missing"].as_u64(),
+            sum: stats["sum"].as_f64(),
+            sum_of_squares: stats["sumOfSquares"].as_f64(),
+            mean: present("mean"),
+            stddev: stats["stddev"].as_f64(),
+            percentiles,
+            cardinality: stats["cardinality"].as_u64(),
+            count_distinct: stats["countDistinct"].as_u64(),
+            distinct_values: stats["distinctValues"].as_array().cloned(),
+            facets,
+        })
+    }
+
+    /// Parses the `stats` section of a query response.
+    ///
+    /// # Arguments
+    /// * `url` - The URL of the request, used in errors.
+    /// * `section` - The section JSON.
+    pub(crate) fn parse_section(url: &str, section: &Value) -> Result<HashMap<String, FieldStats>, SolrError> {
+        let mut fields = HashMap::new();
+        if let Some(stats_fields) = section["stats_fields"].as_object() {
+            for (field, stats) in stats_fields {
+                fields.insert(field.clone(), FieldStats::parse(url, stats)?);
+            }
+        }
+        Ok(fields)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::QueryResponse;
+
+    #[test]
+    fn stats_params_and_response() {
+        let field = StatsField::new("price").percentiles(&[50.0, 99.9]).cardinality(0.5).exclude("a").exclude("b").build();
+        assert_eq!(field, "{!percentiles=50,99.9 cardinality=0.5 ex=a,b}price");
+        assert_eq!(StatsField::new("price").build(), "price");
+
+        let res = json!({
+            "response": { "numFound": 2, "docs": [] },
+            "stats": { "stats_fields": {
+                "price": {
+                    "min": 1.5, "max": 10.0, "count": 2, "missing": 0, "sum": 11.5,
+                    "sumOfSquares": 102.25, "mean": 5.75, "stddev": 6.01,
+                    "percentiles": ["50.0", 5.75, "99.9", 10.0],
+                    "facets": { "cat": { "books": { "min": 1.5, "count": 1 } } }
+                },
+                "released": { "min": "2020-01-01T00:00:00Z", "cardinality": 2 }
+            }}
+        });
+        let stats = QueryResponse::parse("http://localhost", res).unwrap().stats.unwrap();
+        assert_eq!(stats["price"].count, Some(2));
+        assert_eq!(stats["price"].mean, Some(json!(5.75)));
+        assert_eq!(stats["price"].percentiles, vec![(50.0, 5.75), (99.9, 10.0)]);
+        assert_eq!(stats["price"].facets["cat"]["books"].count, Some(1));
+        assert_eq!(stats["released"].min, Some(json!("2020-01-01T00:00:00Z")));
+        assert_eq!(stats["released"].cardinality, Some(2));
+    }
+}
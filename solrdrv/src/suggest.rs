@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use serde::Deserialize;
+use crate::{Auth, Collection, RequestOptions, SolrError};
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+/// A suggestion returned by the suggester
+pub struct SuggestTerm {
+    /// The suggested term.
+    pub term: String,
+    /// The weight of the suggestion.
+    #[serde(default)]
+    pub weight: i64,
+    /// The payload of the suggestion (empty if the dictionary has no payload field).
+    #[serde(default)]
+    pub payload: String,
+}
+
+#[derive(Debug)]
+/// A request of the `/suggest` handler (SuggestComponent), used for autocomplete
+pub struct Suggester<'a, 'b> {
+    collection: &'a Collection<'b>,
+    params: Vec<(String, String)>,
+    options: RequestOptions,
+}
+
+impl<'a, 'b> Suggester<'a, 'b> {
+    pub(crate) fn new(collection: &'a Collection<'b>) -> Suggester<'a, 'b> {
+        Suggester {
+            collection,
+            params: vec![],
+            options: RequestOptions::default(),
+        }
+    }
+
+    fn set<T>(&mut self, param: &str, value: T) -> &mut Self
+        where T: std::string::ToString {
+        let param = format!("suggest.{}", param);
+        self.params.retain(|(p, _)| *p != param);
+        self.params.push((param, value.to_string()));
+        self
+    }
+
+    /// Sets the prefix (or the partial query) to complete.
+    ///
+    /// # Arguments
+    /// * `query` -
+    pub fn query(&mut self, query: &str) -> &mut Self {
+        self.set("q", query)
+    }
+
+    /// Adds a dictionary to use, as configured in `solrconfig.xml`. Can be set multiple times,
+    /// defaults to the dictionaries of the handler.
+    ///
+    /// # Arguments
+    /// * `dictionary` -
+    pub fn dictionary(&mut self, dictionary: &str) -> &mut Self {
+        self.params.push(("suggest.dictionary".into(), dictionary.to_string()));
+        self
+    }
+
+    /// Sets the maximum number of suggestions per dictionary.
+    ///
+    /// # Arguments
+    /// * `count` -
+    pub fn count(&mut self, count: usize) -> &mut Self {
+        self.set("count", count)
+    }
+
+    /// Filters suggestions by a context field query (requires a dictionary with `contextField`).
+    ///
+    /// # Arguments
+    /// * `cfq` -
+    pub fn cfq(&mut self, cfq: &str) -> &mut Self {
+        self.set("cfq", cfq)
+    }
+
+    /// Sets whether to build the dictionaries before suggesting.
+    ///
+    /// # Arguments
+    /// * `build` -
+    pub fn build(&mut self, build: bool) -> &mut Self {
+        self.set("build", build)
+    }
+
+    /// Sets whether to reload the dictionaries before suggesting.
+    ///
+    /// # Arguments
+    /// * `reload` -
+    pub fn reload(&mut self, reload: bool) -> &mut Self {
+        self.set("reload", reload)
+    }
+
+    /// Overrides the client's authentication for the request.
+    ///
+    /// # Arguments
+    /// * `auth` -
+    pub fn auth(&mut self, auth: Auth) -> &mut Self {
+        self.options.auth(auth);
+        self
+    }
+
+    /// Sets a header sent with the request.
+    ///
+    /// # Arguments
+    /// * `name` - The header name.
+    /// * `value` - The header value.
+    pub fn header(&mut self, name: String, value: String) -> &mut Self {
+        self.options.header(name, value);
+        self
+    }
+
+    pub(crate) fn build_path(&self) -> String {
+        let mut path = format!("{}/suggest?suggest=true", self.collection.name);
+        for (param, value) in &self.params {
+            path.push_str(&format!("&{}={}", param, self.collection.client.url_encode(value)));
+        }
+        path
+    }
+
+    /// Commits the request and returns the suggestions keyed by dictionary name. Building the
+    /// dictionaries without a query returns no suggestions.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+    /// # let mut products = solr.collections().get("products".into()).await?;
+    /// let suggestions = products.suggest()
+    ///     .dictionary("productSuggester")
+    ///     .query("elec")
+    ///     .count(5)
+    ///     .commit().await?;
+    /// for suggestion in &suggestions["productSuggester"] {
+    ///     println!("{} ({})", suggestion.term, suggestion.weight);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/suggester.html
+    pub async fn commit(&self) -> Result<HashMap<String, Vec<SuggestTerm>>, SolrError> {
+        let path = self.build_path();
+        let url = self.collection.client.format_url(&path);
+        let res = self.collection.client.get_with(&path, &self.options).await?;
+        let unexpected = || SolrError::UnexpectedResponse {
+            url: url.clone(),
+            message: "malformed `suggest`".into(),
+        };
+
+        let mut suggestions = HashMap::new();
+        let dictionaries = match res.get("suggest") {
+            Some(dictionaries) => dictionaries.as_object().ok_or_else(unexpected)?,
+            None => return Ok(suggestions),
+        };
+        for (dictionary, queries) in dictionaries {
+            let mut terms = vec![];
+            // A single query is sent, but the result is keyed by it
+            for result in queries.as_object().ok_or_else(unexpected)?.values() {
+                let found: Vec<SuggestTerm> = serde_json::from_value(result["suggestions"].clone())
+                    .map_err(|source| SolrError::Json { url: Some(url.clone()), source })?;
+                terms.extend(found);
+            }
+            suggestions.insert(dictionary.clone(), terms);
+        }
+        Ok(suggestions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Solr;
+
+    #[test]
+    fn suggester_path() {
+        let solr = Solr::client("http".into(), "localhost".into(), 8983);
+        let products = Collection::new(&solr, "products".into());
+        assert_eq!(products.suggest().dictionary("names").query("elec tr").count(5).build_path(),
+            "products/suggest?suggest=true&suggest.dictionary=names&suggest.q=elec%20tr&suggest.count=5");
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use serde_json::Value;
+use crate::response::named_list;
+use crate::{Auth, Collection, RequestOptions, SolrError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// An order of terms returned by the terms component
+pub enum TermsSort {
+    /// Orders terms by their document frequency, highest first.
+    Count,
+    /// Orders terms by their byte order in the index.
+    Index,
+}
+
+impl TermsSort {
+    /// Returns the value of the `terms.sort` parameter.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TermsSort::Count => "count",
+            TermsSort::Index => "index",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A term of the index with its frequency
+pub struct TermCount {
+    /// The term.
+    pub term: String,
+    /// The number of documents containing the term.
+    pub df: u64,
+    /// The total number of occurrences of the term (see `Terms::ttf`).
+    pub ttf: Option<u64>,
+}
+
+#[derive(Debug)]
+/// A request of the `/terms` handler (TermsComponent), which enumerates indexed terms of fields
+pub struct Terms<'a, 'b> {
+    collection: &'a Collection<'b>,
+    params: Vec<(String, String)>,
+    options: RequestOptions,
+}
+
+impl<'a, 'b> Terms<'a, 'b> {
+    pub(crate) fn new(collection: &'a Collection<'b>) -> Terms<'a, 'b> {
+        Terms {
+            collection,
+            params: vec![],
+            options: RequestOptions::default(),
+        }
+    }
+
+    fn set<T>(&mut self, param: &str, value: T) -> &mut Self
+        where T: std::string::ToString {
+        let param = format!("terms.{}", param);
+        self.params.retain(|(p, _)| *p != param);
+        self.params.push((param, value.to_string()));
+        self
+    }
+
+    /// Adds a field to enumerate terms of. Can be set multiple times.
+    ///
+    /// # Arguments
+    /// * `field` -
+    pub fn field(&mut self, field: &str) -> &mut Self {
+        self.params.push(("terms.fl".into(), field.to_string()));
+        self
+    }
+
+    /// Restricts terms to those starting with a prefix.
+    ///
+    /// # Arguments
+    /// * `prefix` -
+    pub fn prefix(&mut self, prefix: &str) -> &mut Self {
+        self.set("prefix", prefix)
+    }
+
+    /// Restricts terms to those matching a regular expression.
+    ///
+    /// # Arguments
+    /// * `regex` -
+    pub fn regex(&mut self, regex: &str) -> &mut Self {
+        self.set("regex", regex)
+    }
+
+    /// Sets the minimum document frequency of returned terms. Solr defaults to 1.
+    ///
+    /// # Arguments
+    /// * `mincount` -
+    pub fn mincount(&mut self, mincount: u64) -> &mut Self {
+        self.set("mincount", mincount)
+    }
+
+    /// Sets the maximum number of terms per field, `-1` for unlimited. Solr defaults to 10.
+    ///
+    /// # Arguments
+    /// * `limit` -
+    pub fn limit(&mut self, limit: i64) -> &mut Self {
+        self.set("limit", limit)
+    }
+
+    /// Sets the order of terms. Solr defaults to `TermsSort::Count`.
+    ///
+    /// # Arguments
+    /// * `sort` -
+    pub fn sort(&mut self, sort: TermsSort) -> &mut Self {
+        self.set("sort", sort.as_str())
+    }
+
+    /// Sets whether to return the total term frequency (see `TermCount::ttf`).
+    ///
+    /// # Arguments
+    /// * `ttf` -
+    pub fn ttf(&mut self, ttf: bool) -> &mut Self {
+        self.set("ttf", ttf)
+    }
+
+    /// Overrides the client's authentication for the request.
+    ///
+    /// # Arguments
+    /// * `auth` -
+    pub fn auth(&mut self, auth: Auth) -> &mut Self {
+        self.options.auth(auth);
+        self
+    }
+
+    /// Sets a header sent with the request.
+    ///
+    /// # Arguments
+    /// * `name` - The header name.
+    /// * `value` - The header value.
+    pub fn header(&mut self, name: String, value: String) -> &mut Self {
+        self.options.header(name, value);
+        self
+    }
+
+    pub(crate) fn build_path(&self) -> String {
+        // Flat named lists keep the order of terms
+        let mut path = format!("{}/terms?terms=true&json.nl=flat", self.collection.name);
+        for (param, value) in &self.params {
+            path.push_str(&format!("&{}={}", param, self.collection.client.url_encode(value)));
+        }
+        path
+    }
+
+    /// Commits the request and returns the terms keyed by field name, in the requested order.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use solrdrv::TermsSort;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let solr = solrdrv::Solr::client("http".into(), "localhost".into(), 8983);
+    /// # let mut products = solr.collections().get("products".into()).await?;
+    /// let terms = products.terms()
+    ///     .field("brand")
+    ///     .prefix("a")
+    ///     .sort(TermsSort::Index)
+    ///     .limit(-1)
+    ///     .commit().await?;
+    /// for term in &terms["brand"] {
+    ///     println!("{} ({})", term.term, term.df);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/the-terms-component.html
+    pub async fn commit(&self) -> Result<HashMap<String, Vec<TermCount>>, SolrError> {
+        let path = self.build_path();
+        let url = self.collection.client.format_url(&path);
+        let res = self.collection.client.get_with(&path, &self.options).await?;
+        match res.get("terms") {
+            Some(terms) => parse_terms(terms).ok_or_else(|| SolrError::UnexpectedResponse {
+                url,
+                message: "malformed `terms`".into(),
+            }),
+            None => Ok(HashMap::new()),
+        }
+    }
+}
+
+/// Parses the `terms` section, where each term is followed by its document frequency, or by an
+/// object with `df` and `ttf`.
+pub(crate) fn parse_terms(terms: &Value) -> Option<HashMap<String, Vec<TermCount>>> {
+    named_list(terms)?.into_iter()
+        .map(|(field, list)| {
+            let counts = named_list(list)?.into_iter()
+                .map(|(term, count)| match count {
+                    Value::Object(_) => Some(TermCount { term, df: count["df"].as_u64()?, ttf: count["ttf"].as_u64() }),
+                    _ => Some(TermCount { term, df: count.as_u64()?, ttf: None }),
+                })
+                .collect::<Option<Vec<_>>>()?;
+            Some((field, counts))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::Solr;
+
+    #[test]
+    fn terms_path_and_response() {
+        let solr = Solr::client("http".into(), "localhost".into(), 8983);
+        let products = Collection::new(&solr, "products".into());
+        assert_eq!(products.terms().field("brand").field("cat").prefix("a").sort(TermsSort::Index).limit(-1).build_path(),
+            "products/terms?terms=true&json.nl=flat&terms.fl=brand&terms.fl=cat&terms.prefix=a&terms.sort=index&terms.limit=%2D1");
+
+        let terms = parse_terms(&json!({
+            "brand": ["zeta", 5, "acme", 3],
+            "cat": ["books", { "df": 2, "ttf": 7 }]
+        })).unwrap();
+        assert_eq!(terms["brand"].iter().map(|t| t.term.as_str()).collect::<Vec<_>>(), vec!["zeta", "acme"]);
+        assert_eq!(terms["brand"][1], TermCount { term: "acme".into(), df: 3, ttf: None });
+        assert_eq!(terms["cat"][0], TermCount { term: "books".into(), df: 2, ttf: Some(7) });
+    }
+}
@@ -0,0 +1,334 @@
+use serde_json::{json, Map, Value};
+use crate::{SolrError, VERSION_FIELD};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A modifier of an atomic update
+pub enum Modifier {
+    /// Sets or replaces the field value(s), or removes the field if the value is `null`.
+    Set,
+    /// Adds the specified value(s) to a multi-valued field.
+    Add,
+    /// Adds the specified value(s) to a multi-valued field, only if not already present.
+    AddDistinct,
+    /// Removes all occurrences of the specified value(s) from a multi-valued field.
+    Remove,
+    /// Removes all occurrences of values matching the specified regular expression(s).
+    RemoveRegex,
+    /// Increments a numeric value by the specified amount.
+    Inc,
+}
+
+impl Modifier {
+    /// Returns the name of the modifier used by Solr.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Modifier::Set => "set",
+            Modifier::Add => "add",
+            Modifier::AddDistinct => "add-distinct",
+            Modifier::Remove => "remove",
+            Modifier::RemoveRegex => "removeregex",
+            Modifier::Inc => "inc",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A builder for atomic (partial) updates of a single document
+pub struct AtomicUpdate {
+    key_field: String,
+    id: String,
+    fields: Map<String, Value>,
+    version: Option<i64>,
+}
+
+impl AtomicUpdate {
+    /// Creates a new atomic update of a document whose unique key field is `id`.
+    ///
+    /// # Arguments
+    /// * `id` - The unique key of the document to update.
+    pub fn new(id: &str) -> AtomicUpdate {
+        AtomicUpdate::with_key("id", id)
+    }
+
+    /// Creates a new atomic update of a document with a custom unique key field.
+    ///
+    /// # Arguments
+    /// * `key_field` - The name of the unique key field.
+    /// * `id` - The unique key of the document to update.
+    pub fn with_key(key_field: &str, id: &str) -> AtomicUpdate {
+        AtomicUpdate {
+            key_field: key_field.to_string(),
+            id: id.to_string(),
+            fields: Map::new(),
+            version: None,
+        }
+    }
+
+    /// Applies a modifier to a field. Multiple modifiers can be applied to the same field.
+    ///
+    /// # Arguments
+    /// * `field` - The field name.
+    /// * `modifier` -
+    /// * `value` - A single value or an array of values.
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/updating-parts-of-documents.html#atomic-updates
+    pub fn modify<T>(&mut self, field: &str, modifier: Modifier, value: T) -> &mut Self
+        where T: serde::ser::Serialize {
+        let entry = self.fields.entry(field.to_string()).or_insert_with(|| json!({}));
+        entry[modifier.as_str()] = json!(value);
+        self
+    }
+
+    /// Sets or replaces the value(s) of a field. Use `null` to remove the field.
+    ///
+    /// # Arguments
+    /// * `field` -
+    /// * `value` -
+    pub fn set<T>(&mut self, field: &str, value: T) -> &mut Self
+        where T: serde::ser::Serialize {
+        self.modify(field, Modifier::Set, value)
+    }
+
+    /// Adds value(s) to a multi-valued field.
+    ///
+    /// # Arguments
+    /// * `field` -
+    /// * `value` -
+    pub fn add<T>(&mut self, field: &str, value: T) -> &mut Self
+        where T: serde::ser::Serialize {
+        self.modify(field, Modifier::Add, value)
+    }
+
+    /// Adds value(s) to a multi-valued field, only if not already present.
+    ///
+    /// # Arguments
+    /// * `field` -
+    /// * `value` -
+    pub fn add_distinct<T>(&mut self, field: &str, value: T) -> &mut Self
+        where T: serde::ser::Serialize {
+        self.modify(field, Modifier::AddDistinct, value)
+    }
+
+    /// Removes all occurrences of value(s) from a multi-valued field.
+    ///
+    /// # Arguments
+    /// * `field` -
+    /// * `value` -
+    pub fn remove<T>(&mut self, field: &str, value: T) -> &mut Self
+        where T: serde::ser::Serialize {
+        self.modify(field, Modifier::Remove, value)
+    }
+
+    /// Removes all occurrences of values matching regular expression(s) from a multi-valued field.
+    ///
+    /// # Arguments
+    /// * `field` -
+    /// * `regex` - A single regular expression or an array of regular expressions.
+    pub fn remove_regex<T>(&mut self, field: &str, regex: T) -> &mut Self
+        where T: serde::ser::Serialize {
+        self.modify(field, Modifier::RemoveRegex, regex)
+    }
+
+    /// Increments a numeric field by the specified amount (use a negative amount to decrement).
+    ///
+    /// # Arguments
+    /// * `field` -
+    /// * `amount` -
+    pub fn inc<T>(&mut self, field: &str, amount: T) -> &mut Self
+        where T: serde::ser::Serialize {
+        self.modify(field, Modifier::Inc, amount)
+    }
+
+    /// Applies the update only if the `_version_` constraint is met, otherwise the commit fails
+    /// with `SolrError::VersionConflict`.
+    ///
+    /// # Arguments
+    /// * `version` - A positive version must match the version of the existing document exactly,
+    ///   `1` requires the document to exist, a negative version requires the document to not exist.
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/updating-parts-of-documents.html#optimistic-concurrency
+    pub fn version(&mut self, version: i64) -> &mut Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// Builds the update document.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # fn run() -> Result<(), solrdrv::SolrError> {
+    /// let update = solrdrv::AtomicUpdate::new("1")
+    ///     .inc("views", 1)
+    ///     .add_distinct("tags", vec!["new", "hot"])
+    ///     .build()?;
+    /// // => { "id": "1", "views": { "inc": 1 }, "tags": { "add-distinct": ["new", "hot"] } }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Return
+    /// If no field is modified, then returns `SolrError::InvalidInput`, since such document would
+    /// replace the whole existing document.
+    pub fn build(&self) -> Result<Value, SolrError> {
+        if self.fields.is_empty() {
+            return Err(SolrError::InvalidInput(
+                format!("atomic update of document `{}` does not modify any field", self.id)));
+        }
+        if self.fields.contains_key(&self.key_field) {
+            return Err(SolrError::InvalidInput(
+                format!("atomic update cannot modify the unique key field `{}`", self.key_field)));
+        }
+        let mut doc = self.fields.clone();
+        doc.insert(self.key_field.clone(), json!(self.id));
+        if let Some(version) = self.version {
+            doc.insert(VERSION_FIELD.into(), json!(version));
+        }
+        Ok(Value::Object(doc))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Defines when documents sent by an update become visible to searches
+pub enum CommitStrategy {
+    /// Does not commit, documents become visible on the next (auto) commit.
+    None,
+    /// Performs a hard commit, which flushes the documents to stable storage.
+    Hard,
+    /// Performs a soft commit, which makes the documents visible without flushing them.
+    Soft,
+    /// Commits within the specified number of milliseconds.
+    Within(u64),
+}
+
+#[derive(Debug, Clone)]
+/// Options of a commit following an update
+pub struct CommitOptions {
+    strategy: CommitStrategy,
+    open_searcher: Option<bool>,
+    wait_searcher: Option<bool>,
+}
+
+impl CommitOptions {
+    /// Creates new commit options.
+    ///
+    /// # Arguments
+    /// * `strategy` -
+    pub fn new(strategy: CommitStrategy) -> CommitOptions {
+        CommitOptions {
+            strategy,
+            open_searcher: None,
+            wait_searcher: None,
+        }
+    }
+
+    /// Returns options which do not commit.
+    pub fn none() -> CommitOptions {
+        CommitOptions::new(CommitStrategy::None)
+    }
+
+    /// Returns options performing a hard commit.
+    pub fn hard() -> CommitOptions {
+        CommitOptions::new(CommitStrategy::Hard)
+    }
+
+    /// Returns options performing a soft commit.
+    pub fn soft() -> CommitOptions {
+        CommitOptions::new(CommitStrategy::Soft)
+    }
+
+    /// Returns options committing within the specified number of milliseconds.
+    ///
+    /// # Arguments
+    /// * `ms` -
+    pub fn within(ms: u64) -> CommitOptions {
+        CommitOptions::new(CommitStrategy::Within(ms))
+    }
+
+    /// Sets whether a hard commit opens a new searcher, i.e. makes the documents visible. Use
+    /// `false` to only flush the documents to stable storage.
+    ///
+    /// # Arguments
+    /// * `open_searcher` -
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/uploading-data-with-index-handlers.html#commit-and-optimize-during-updates
+    pub fn open_searcher(&mut self, open_searcher: bool) -> &mut Self {
+        self.open_searcher = Some(open_searcher);
+        self
+    }
+
+    /// Sets whether the request blocks until a new searcher is opened and registered.
+    ///
+    /// # Arguments
+    /// * `wait_searcher` -
+    ///
+    /// # See
+    /// https://lucene.apache.org/solr/guide/8_5/uploading-data-with-index-handlers.html#commit-and-optimize-during-updates
+    pub fn wait_searcher(&mut self, wait_searcher: bool) -> &mut Self {
+        self.wait_searcher = Some(wait_searcher);
+        self
+    }
+
+    /// Returns the options as a query string of the `/update` handler (including the leading `?`),
+    /// or an empty string if there are no parameters.
+    pub(crate) fn to_query_string(&self) -> String {
+        let mut params = vec![];
+        match self.strategy {
+            CommitStrategy::None => {},
+            CommitStrategy::Hard => params.push("commit=true".to_string()),
+            CommitStrategy::Soft => params.push("softCommit=true".to_string()),
+            CommitStrategy::Within(ms) => params.push(format!("commitWithin={}", ms)),
+        }
+        if let Some(open_searcher) = self.open_searcher {
+            params.push(format!("openSearcher={}", open_searcher));
+        }
+        if let Some(wait_searcher) = self.wait_searcher {
+            params.push(format!("waitSearcher={}", wait_searcher));
+        }
+        if params.is_empty() {
+            return String::new();
+        }
+        format!("?{}", params.join("&"))
+    }
+}
+
+impl Default for CommitOptions {
+    /// Returns options performing a hard commit.
+    fn default() -> CommitOptions {
+        CommitOptions::hard()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn atomic_update_build() {
+        let doc = AtomicUpdate::new("1")
+            .set("name", "Some")
+            .inc("age", 1)
+            .add_distinct("tags", vec!["a", "b"])
+            .remove("tags", "c")
+            .build().unwrap();
+        assert_eq!(doc, json!({
+            "id": "1",
+            "name": { "set": "Some" },
+            "age": { "inc": 1 },
+            "tags": { "add-distinct": ["a", "b"], "remove": "c" }
+        }));
+        assert!(AtomicUpdate::new("1").build().is_err());
+    }
+
+    #[test]
+    fn commit_options_query_string() {
+        assert_eq!(CommitOptions::default().to_query_string(), "?commit=true");
+        assert_eq!(CommitOptions::none().to_query_string(), "");
+        assert_eq!(CommitOptions::within(500).to_query_string(), "?commitWithin=500");
+        assert_eq!(CommitOptions::hard().open_searcher(false).wait_searcher(true).to_query_string(),
+            "?commit=true&openSearcher=false&waitSearcher=true");
+    }
+}
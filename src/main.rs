@@ -11,16 +11,13 @@ use solrdrv::{
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let solr = Solr::client("http".into(), "localhost".into(), 8983);
 
-    match solr.collections().get("users".into()).await {
-        Ok(col) => {
-            col.schema()
-                .delete_field("name")
-                .delete_field("age")
-                .commit().await?;
-            solr.collections().delete(&col.name).await?;
-        },
-        Err(_) => {}
-    };
+    if let Ok(col) = solr.collections().get("users".into()).await {
+        col.schema()
+            .delete_field("name")
+            .delete_field("age")
+            .commit().await?;
+        solr.collections().delete(&col.name).await?;
+    }
 
     let mut users = solr.collections()
         .create("users".into())